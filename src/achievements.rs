@@ -0,0 +1,248 @@
+use crate::binary;
+use crate::storage;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = *b"SNAC";
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Index of the hard difficulty in the start menu
+const HARD: u8 = 2;
+
+/// Every achievement there is, in the order they're listed in the gallery
+pub const ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement {
+        id: "first-apple",
+        name: "Appetizer",
+        description: "Eat an apple",
+        condition: Condition::Apples(1),
+        difficulty: None,
+    },
+    Achievement {
+        id: "combo-8",
+        name: "Combo Breaker",
+        description: "Build a combo up to x8",
+        condition: Condition::Combo(8),
+        difficulty: None,
+    },
+    Achievement {
+        id: "length-25",
+        name: "Growing Up",
+        description: "Grow to a length of 25",
+        condition: Condition::Length(25),
+        difficulty: None,
+    },
+    Achievement {
+        id: "length-50",
+        name: "Long Boi",
+        description: "Grow to a length of 50",
+        condition: Condition::Length(50),
+        difficulty: None,
+    },
+    Achievement {
+        id: "score-10000",
+        name: "High Roller",
+        description: "Score 10000 points in one round",
+        condition: Condition::Score(10000),
+        difficulty: None,
+    },
+    Achievement {
+        id: "straight-hard",
+        name: "Steady Nerves",
+        description: "Go 10 seconds straight and win on Hard",
+        condition: Condition::WonStraight(10 * 60),
+        difficulty: Some(HARD),
+    },
+    Achievement {
+        id: "board-cleared",
+        name: "Clean Sweep",
+        description: "Clear the board",
+        condition: Condition::ClearBoard,
+        difficulty: None,
+    },
+    Achievement {
+        id: "last-cycle",
+        name: "End of Line",
+        description: "Outlast every rival in Tron",
+        condition: Condition::OutlastRivals,
+        difficulty: None,
+    },
+];
+
+/// What has to happen to the snake of a player in a round for an achievement
+/// to be unlocked
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Condition {
+    Length(usize),      // grow to at least this length
+    Score(usize),       // score at least this many points in a round
+    Apples(usize),      // eat at least this many apples in a round
+    Combo(usize),       // build the combo multiplier up to at least this
+    WonStraight(usize), // win a round after going at least this many frames without turning
+    ClearBoard,
+    OutlastRivals, // be the last cycle riding in a tron round
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Achievement {
+    pub id: &'static str, // kept in the achievements file, so never changed
+    pub name: &'static str,
+    pub description: &'static str,
+    pub condition: Condition,
+    pub difficulty: Option<u8>, // only counts on this difficulty, if any
+}
+
+/// Something that happened to the snake of a player in a round
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event {
+    Length(usize),      // the snake is now
+    Score(usize),       // of the round so far
+    Apples(usize),      // eaten in the round so far
+    Combo(usize),       // the multiplier is now at
+    WonStraight(usize), // the round, with the most frames the snake went without turning
+    ClearedBoard,
+    OutlastedRivals,
+}
+
+impl Condition {
+    fn is_met_by(&self, event: Event) -> bool {
+        match (*self, event) {
+            (Condition::Length(needed), Event::Length(length)) => length >= needed,
+            (Condition::Score(needed), Event::Score(score)) => score >= needed,
+            (Condition::Apples(needed), Event::Apples(apples)) => apples >= needed,
+            (Condition::Combo(needed), Event::Combo(multiplier)) => multiplier >= needed,
+            (Condition::WonStraight(needed), Event::WonStraight(frames)) => frames >= needed,
+            (Condition::ClearBoard, Event::ClearedBoard) => true,
+            (Condition::OutlastRivals, Event::OutlastedRivals) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The achievements that have been unlocked so far
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Unlocked {
+    ids: BTreeSet<String>,
+}
+
+impl Unlocked {
+    pub fn contains(&self, achievement: &Achievement) -> bool {
+        self.ids.contains(achievement.id)
+    }
+
+    pub fn count(&self) -> usize {
+        ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| self.contains(achievement))
+            .count()
+    }
+
+    /// Adds the achievements unlocked in `other` to these
+    pub fn merge(&mut self, other: Unlocked) {
+        self.ids.extend(other.ids);
+    }
+
+    /// Unlocks the achievements that any of `events`, which happened in a
+    /// round on `difficulty`, meets. Returns the ones that weren't unlocked
+    /// already.
+    pub fn unlock(&mut self, events: &[Event], difficulty: u8) -> Vec<&'static Achievement> {
+        let mut unlocked = Vec::new();
+        for achievement in ACHIEVEMENTS.iter() {
+            let counts = achievement.difficulty.is_none_or(|only| only == difficulty);
+            let met = events
+                .iter()
+                .any(|&event| achievement.condition.is_met_by(event));
+            if counts && met && self.ids.insert(achievement.id.to_string()) {
+                unlocked.push(achievement);
+            }
+        }
+        unlocked
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        storage::write_versioned(&mut bytes, MAGIC, FORMAT_VERSION);
+        bytes.push(self.ids.len() as u8);
+        for id in &self.ids {
+            bytes.push(id.len() as u8);
+            bytes.extend_from_slice(id.as_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        storage::read_versioned(reader, MAGIC, FORMAT_VERSION, "achievements")?;
+        let mut unlocked = Unlocked::default();
+        let [num_ids] = binary::read_bytes(reader)?;
+        for _ in 0..num_ids {
+            let [len] = binary::read_bytes(reader)?;
+            let mut id = vec![0; len as usize];
+            reader.read_exact(&mut id)?;
+            let id = String::from_utf8(id)
+                .map_err(|_| binary::invalid_data("achievement id isn't text".to_string()))?;
+            unlocked.ids.insert(id);
+        }
+        Ok(unlocked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn achievements_have_ids_of_their_own() {
+        let ids: BTreeSet<&str> = ACHIEVEMENTS
+            .iter()
+            .map(|achievement| achievement.id)
+            .collect();
+        assert_eq!(ids.len(), ACHIEVEMENTS.len());
+    }
+
+    #[test]
+    fn achievements_unlock_once() {
+        let mut unlocked = Unlocked::default();
+        let names = |achievements: Vec<&Achievement>| {
+            achievements
+                .iter()
+                .map(|achievement| achievement.name)
+                .collect::<Vec<_>>()
+        };
+        let events = [Event::Length(30), Event::Apples(0)];
+        assert_eq!(names(unlocked.unlock(&events, 1)), vec!["Growing Up"]);
+        assert!(unlocked.unlock(&events, 1).is_empty());
+        assert_eq!(unlocked.count(), 1);
+    }
+
+    #[test]
+    fn some_achievements_only_count_on_one_difficulty() {
+        let mut unlocked = Unlocked::default();
+        let events = [Event::WonStraight(20 * 60)];
+        assert!(unlocked.unlock(&events, 0).is_empty());
+        assert_eq!(unlocked.unlock(&events, HARD).len(), 1);
+    }
+
+    #[test]
+    fn merging_keeps_the_unlocks_of_both() {
+        let mut unlocked = Unlocked::default();
+        unlocked.unlock(&[Event::Apples(1)], 0);
+        let mut other = Unlocked::default();
+        other.unlock(&[Event::ClearedBoard], 0);
+        unlocked.merge(other);
+        assert_eq!(unlocked.count(), 2);
+    }
+
+    #[test]
+    fn unlocks_survive_round_trip() {
+        let mut unlocked = Unlocked::default();
+        unlocked.unlock(&[Event::ClearedBoard, Event::Combo(8)], 0);
+        let mut bytes = Vec::new();
+        unlocked.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            Unlocked::read_from(&mut Cursor::new(bytes)).unwrap(),
+            unlocked
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use crate::rectilinear::Direction;
+use crate::rectilinear::RectilinearLine;
+use glam::i32;
+use glam::IVec2;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::VecDeque;
+use strum::IntoEnumIterator;
+
+/// How cleverly a computer controlled snake plays
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AiPersonality {
+    /// Heads for the apple along the shortest path without looking out for
+    /// dead ends, and now and then takes a random turn.
+    Easy,
+    /// Heads for the apple along the shortest path, unless following it would
+    /// lead into an area too small to fit the snake.
+    Normal,
+    /// Like `Normal` but using A*, keeping away from the heads of other snakes
+    /// and asking for more room before committing to a path.
+    Hard,
+}
+
+/// Occupancy grid of the play area used for path finding, where the top left
+/// cell of the play area is (0, 0).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32) -> Self {
+        Grid {
+            width,
+            height,
+            blocked: vec![false; (width * height) as usize],
+        }
+    }
+
+    pub fn contains(&self, pos: IVec2) -> bool {
+        (0..self.width).contains(&pos.x) && (0..self.height).contains(&pos.y)
+    }
+
+    /// A cell is free if it's inside the grid and not blocked
+    pub fn is_free(&self, pos: IVec2) -> bool {
+        self.contains(pos) && !self.blocked[self.index(pos)]
+    }
+
+    /// Marks the cell at `pos` as blocked, ignoring cells outside the grid
+    pub fn block(&mut self, pos: IVec2) {
+        if self.contains(pos) {
+            let index = self.index(pos);
+            self.blocked[index] = true;
+        }
+    }
+
+    /// Marks every cell covered by `line` as blocked
+    pub fn block_line(&mut self, line: &RectilinearLine) {
+        for point in line.points() {
+            self.block(point);
+        }
+    }
+
+    fn index(&self, pos: IVec2) -> usize {
+        (pos.y * self.width + pos.x) as usize
+    }
+
+    fn pos(&self, index: usize) -> IVec2 {
+        i32::ivec2(index as i32 % self.width, index as i32 / self.width)
+    }
+}
+
+/// Finds the shortest path from `from` to `to` through free cells with a
+/// breadth-first search, and returns the direction of the first step.
+pub fn bfs_first_step(grid: &Grid, from: IVec2, to: IVec2) -> Option<Direction> {
+    if !grid.is_free(to) {
+        return None;
+    }
+
+    let mut came_from: Vec<Option<usize>> = vec![None; grid.blocked.len()];
+    let mut queue = VecDeque::new();
+    let start = grid.index(from);
+    came_from[start] = Some(start);
+    queue.push_back(from);
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == to {
+            return first_step(grid, &came_from, from, to);
+        }
+        for dir in Direction::iter() {
+            let next = pos + dir.unit();
+            if grid.is_free(next) && came_from[grid.index(next)].is_none() {
+                came_from[grid.index(next)] = Some(grid.index(pos));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the shortest path from `from` to `to` through free cells with an A*
+/// search guided by the manhattan distance, and returns the direction of the
+/// first step.
+pub fn astar_first_step(grid: &Grid, from: IVec2, to: IVec2) -> Option<Direction> {
+    if !grid.is_free(to) {
+        return None;
+    }
+
+    let heuristic = |pos: IVec2| (to.x - pos.x).abs() + (to.y - pos.y).abs();
+    let mut came_from: Vec<Option<usize>> = vec![None; grid.blocked.len()];
+    let mut cost = vec![i32::MAX; grid.blocked.len()];
+    let mut open = BinaryHeap::new();
+    let start = grid.index(from);
+    came_from[start] = Some(start);
+    cost[start] = 0;
+    open.push(Reverse((heuristic(from), start)));
+
+    while let Some(Reverse((_, index))) = open.pop() {
+        let pos = grid.pos(index);
+        if pos == to {
+            return first_step(grid, &came_from, from, to);
+        }
+        for dir in Direction::iter() {
+            let next = pos + dir.unit();
+            if !grid.is_free(next) {
+                continue;
+            }
+            let next_index = grid.index(next);
+            let next_cost = cost[index] + 1;
+            if next_cost < cost[next_index] {
+                cost[next_index] = next_cost;
+                came_from[next_index] = Some(index);
+                open.push(Reverse((next_cost + heuristic(next), next_index)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Walks back along a found path from `to` until reaching the cell right after
+/// `from`, and returns the direction of that step.
+fn first_step(
+    grid: &Grid,
+    came_from: &[Option<usize>],
+    from: IVec2,
+    to: IVec2,
+) -> Option<Direction> {
+    let start = grid.index(from);
+    let mut index = grid.index(to);
+    while let Some(prev) = came_from[index] {
+        if prev == start {
+            break;
+        }
+        index = prev;
+    }
+    let step = grid.pos(index) - from;
+    Direction::iter().find(|dir| dir.unit() == step)
+}
+
+/// Counts the free cells that can be reached from `from`, including `from`
+/// itself as long as it's free.
+pub fn flood_fill(grid: &Grid, from: IVec2) -> usize {
+    if !grid.is_free(from) {
+        return 0;
+    }
+
+    let mut visited = vec![false; grid.blocked.len()];
+    let mut stack = vec![from];
+    visited[grid.index(from)] = true;
+    let mut count = 0;
+
+    while let Some(pos) = stack.pop() {
+        count += 1;
+        for dir in Direction::iter() {
+            let next = pos + dir.unit();
+            if grid.is_free(next) && !visited[grid.index(next)] {
+                visited[grid.index(next)] = true;
+                stack.push(next);
+            }
+        }
+    }
+
+    count
+}
+
+/// What the path finding needs to know about the snake it's steering
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AiSnake {
+    pub head: IVec2,
+    pub direction: Direction,
+    pub len: usize,
+}
+
+/// Decides which direction a computer controlled snake should move in next.
+///
+/// The snake tries to reach `target` if there is one. All cells occupied by
+/// walls and snakes (including this snake) are expected to be blocked in
+/// `grid`.
+pub fn choose_direction<R: Rng>(
+    personality: AiPersonality,
+    grid: &Grid,
+    snake: AiSnake,
+    target: Option<IVec2>,
+    other_heads: &[IVec2],
+    rng: &mut R,
+) -> Direction {
+    let AiSnake {
+        head,
+        direction,
+        len: body_len,
+    } = snake;
+    let mut grid = grid.clone();
+    if personality == AiPersonality::Hard {
+        // avoid head-on collisions by keeping out of reach of other heads
+        let mut cautious_grid = grid.clone();
+        for other_head in other_heads {
+            for dir in Direction::iter() {
+                cautious_grid.block(*other_head + dir.unit());
+            }
+        }
+        if !safe_moves(&cautious_grid, head, direction).is_empty() {
+            grid = cautious_grid;
+        }
+    }
+
+    let moves = safe_moves(&grid, head, direction);
+    if moves.is_empty() {
+        // nowhere to go, so just keep going
+        return direction;
+    }
+
+    let path_step = target
+        .and_then(|target| match personality {
+            AiPersonality::Easy | AiPersonality::Normal => bfs_first_step(&grid, head, target),
+            AiPersonality::Hard => astar_first_step(&grid, head, target),
+        })
+        .filter(|step| moves.contains(step));
+
+    match personality {
+        AiPersonality::Easy => {
+            if rng.gen_range(0..10) == 0 {
+                return moves[rng.gen_range(0..moves.len())];
+            }
+            path_step
+                .or_else(|| moves.iter().copied().find(|&dir| dir == direction))
+                .unwrap_or(moves[0])
+        }
+        AiPersonality::Normal | AiPersonality::Hard => {
+            let required_space = match personality {
+                AiPersonality::Hard => 2 * body_len,
+                _ => body_len,
+            };
+            if let Some(step) = path_step {
+                if space_after_move(&grid, head, step) >= required_space {
+                    return step;
+                }
+            }
+
+            // no safe path to the target, so head for the most open space
+            *moves
+                .iter()
+                .max_by_key(|&&dir| space_after_move(&grid, head, dir))
+                .unwrap()
+        }
+    }
+}
+
+/// All directions the snake can move in without immediately crashing
+fn safe_moves(grid: &Grid, head: IVec2, direction: Direction) -> Vec<Direction> {
+    Direction::iter()
+        .filter(|&dir| dir != direction.opposite() && grid.is_free(head + dir.unit()))
+        .collect()
+}
+
+/// The amount of free space reachable after moving one step in `dir`
+fn space_after_move(grid: &Grid, head: IVec2, dir: Direction) -> usize {
+    flood_fill(grid, head + dir.unit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectilinear::ChainedLineSegment;
+    use rand::rngs::mock::StepRng;
+
+    /// Grid with a vertical wall at x = 2 from the top down to y = 3
+    ///
+    /// ..#...
+    /// ..#...
+    /// ..#...
+    /// ..#...
+    /// ......
+    fn walled_grid() -> Grid {
+        let mut grid = Grid::new(6, 5);
+        grid.block_line(&RectilinearLine {
+            start: i32::ivec2(2, 0),
+            segments: VecDeque::from(vec![seg!(Direction::Down, 3)]),
+        });
+        grid
+    }
+
+    #[test]
+    fn cells_outside_grid_are_not_free() {
+        let grid = Grid::new(3, 3);
+        assert_eq!(grid.is_free(i32::ivec2(0, 0)), true);
+        assert_eq!(grid.is_free(i32::ivec2(-1, 0)), false);
+        assert_eq!(grid.is_free(i32::ivec2(0, 3)), false);
+    }
+
+    #[test]
+    fn blocking_a_line_blocks_all_its_cells() {
+        let grid = walled_grid();
+        for y in 0..4 {
+            assert_eq!(grid.is_free(i32::ivec2(2, y)), false);
+        }
+        assert_eq!(grid.is_free(i32::ivec2(2, 4)), true);
+    }
+
+    #[test]
+    fn bfs_steps_straight_towards_target_in_open_grid() {
+        let grid = Grid::new(5, 5);
+        let step = bfs_first_step(&grid, i32::ivec2(0, 2), i32::ivec2(4, 2));
+        assert_eq!(step, Some(Direction::Right));
+    }
+
+    #[test]
+    fn path_finding_goes_around_walls() {
+        let grid = walled_grid();
+        let from = i32::ivec2(1, 0);
+        let to = i32::ivec2(3, 0);
+        assert_eq!(bfs_first_step(&grid, from, to), Some(Direction::Down));
+        assert_eq!(astar_first_step(&grid, from, to), Some(Direction::Down));
+    }
+
+    #[test]
+    fn path_finding_gives_up_on_unreachable_target() {
+        let mut grid = Grid::new(5, 1);
+        grid.block(i32::ivec2(2, 0));
+        let from = i32::ivec2(0, 0);
+        let to = i32::ivec2(4, 0);
+        assert_eq!(bfs_first_step(&grid, from, to), None);
+        assert_eq!(astar_first_step(&grid, from, to), None);
+    }
+
+    #[test]
+    fn flood_fill_counts_reachable_cells() {
+        let mut grid = walled_grid();
+        assert_eq!(flood_fill(&grid, i32::ivec2(0, 0)), 6 * 5 - 4);
+
+        // close off the gap below the wall
+        grid.block(i32::ivec2(2, 4));
+        assert_eq!(flood_fill(&grid, i32::ivec2(0, 0)), 2 * 5);
+        assert_eq!(flood_fill(&grid, i32::ivec2(2, 0)), 0);
+    }
+
+    #[test]
+    fn ai_avoids_dead_end_even_if_target_is_in_it() {
+        // snake head at (1, 2) moving up, apple inside a closed pocket to the
+        // left that the snake can't fit into
+        //
+        // .#....
+        // .#....
+        // .H....
+        // ##....
+        // ......
+        let mut grid = Grid::new(6, 5);
+        grid.block(i32::ivec2(1, 0));
+        grid.block(i32::ivec2(1, 1));
+        grid.block(i32::ivec2(1, 2));
+        grid.block(i32::ivec2(0, 3));
+        grid.block(i32::ivec2(1, 3));
+        let head = i32::ivec2(1, 2);
+        let target = Some(i32::ivec2(0, 0));
+        let mut rng = StepRng::new(1, 1);
+
+        let dir = choose_direction(
+            AiPersonality::Normal,
+            &grid,
+            AiSnake {
+                head,
+                direction: Direction::Up,
+                len: 5,
+            },
+            target,
+            &[],
+            &mut rng,
+        );
+        assert_eq!(dir, Direction::Right);
+    }
+
+    #[test]
+    fn ai_never_reverses_into_itself() {
+        let grid = Grid::new(5, 5);
+        let mut rng = StepRng::new(1, 1);
+        let dir = choose_direction(
+            AiPersonality::Normal,
+            &grid,
+            AiSnake {
+                head: i32::ivec2(2, 2),
+                direction: Direction::Right,
+                len: 3,
+            },
+            Some(i32::ivec2(0, 2)),
+            &[],
+            &mut rng,
+        );
+        assert_ne!(dir, Direction::Left);
+    }
+
+    #[test]
+    fn hard_ai_keeps_away_from_other_heads() {
+        // the target is straight ahead, but so is the head of another snake
+        let grid = Grid::new(7, 5);
+        let mut rng = StepRng::new(1, 1);
+        let dir = choose_direction(
+            AiPersonality::Hard,
+            &grid,
+            AiSnake {
+                head: i32::ivec2(1, 2),
+                direction: Direction::Right,
+                len: 3,
+            },
+            Some(i32::ivec2(5, 2)),
+            &[i32::ivec2(3, 2)],
+            &mut rng,
+        );
+        assert_ne!(dir, Direction::Right);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::attributes;
+use crate::graphics::Canvas;
+use pancurses::chtype;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::io::Write;
+use std::rc::Rc;
+
+/// Curses keeps the color pair of an attribute in its top byte
+const COLOR_SHIFT: u32 = 24;
+
+/// Color pairs below this are left uncolored, since only the pairs from here
+/// and up are set up to draw their color on black
+const FIRST_COLOR_PAIR: chtype = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Glyph {
+    ch: char,
+    attributes: chtype,
+}
+
+const BLANK: Glyph = Glyph {
+    ch: ' ',
+    attributes: attributes::A_NORMAL,
+};
+
+/// A canvas that draws on a remote terminal by writing ANSI escape codes to
+/// `writer`. Everything is drawn into a buffer first, and on refresh only the
+/// cells that changed since the last refresh are sent.
+#[derive(Debug)]
+pub struct AnsiCanvas<W: Write> {
+    writer: RefCell<W>,
+    size: Rc<Cell<(i32, i32)>>, // (lines, columns) of the terminal
+    buffer_size: Cell<(i32, i32)>,
+    attributes: Cell<chtype>,
+    cells: RefCell<Vec<Glyph>>,
+    shown: RefCell<Vec<Glyph>>, // what the terminal is showing right now
+    needs_redraw: Cell<bool>,
+    broken: Cell<bool>,
+}
+
+impl<W: Write> AnsiCanvas<W> {
+    /// Creates a canvas writing to `writer`. The terminal `size` is shared so
+    /// that whoever finds out about the terminal being resized can update it.
+    pub fn new(writer: W, size: Rc<Cell<(i32, i32)>>) -> Self {
+        let (lines, columns) = size.get();
+        let cell_count = (lines * columns).max(0) as usize;
+        AnsiCanvas {
+            writer: RefCell::new(writer),
+            buffer_size: Cell::new(size.get()),
+            size,
+            attributes: Cell::new(attributes::A_NORMAL),
+            cells: RefCell::new(vec![BLANK; cell_count]),
+            shown: RefCell::new(vec![BLANK; cell_count]),
+            needs_redraw: Cell::new(true),
+            broken: Cell::new(false),
+        }
+    }
+
+    /// Whether writing to the terminal has failed, which usually means that
+    /// the other end has gone away
+    pub fn is_broken(&self) -> bool {
+        self.broken.get()
+    }
+
+    /// Changes the size of the terminal, taking effect on the next clear
+    pub fn resize(&self, lines: i32, columns: i32) {
+        self.size.set((lines, columns));
+    }
+
+    /// Makes the next refresh send the whole screen instead of only what
+    /// changed, such as when someone new starts watching
+    pub fn redraw(&self) {
+        self.needs_redraw.set(true);
+    }
+
+    pub fn writer(&self) -> RefMut<'_, W> {
+        self.writer.borrow_mut()
+    }
+
+    /// Leaves the terminal the way it was found, with the cursor showing
+    pub fn finish(&self) {
+        self.write("\x1b[0m\x1b[2J\x1b[H\x1b[?25h");
+    }
+
+    fn put(&self, y: i32, x: i32, ch: char) {
+        let (lines, columns) = self.buffer_size.get();
+        if (0..lines).contains(&y) && (0..columns).contains(&x) {
+            let attributes = self.attributes.get();
+            self.cells.borrow_mut()[(y * columns + x) as usize] = Glyph { ch, attributes };
+        }
+    }
+
+    fn write(&self, text: &str) {
+        let mut writer = self.writer.borrow_mut();
+        let result = writer
+            .write_all(text.as_bytes())
+            .and_then(|_| writer.flush());
+        if result.is_err() {
+            self.broken.set(true);
+        }
+    }
+}
+
+impl<W: Write> Canvas for AnsiCanvas<W> {
+    fn attron(&self, attributes: chtype) {
+        self.attributes.set(self.attributes.get() | attributes);
+    }
+
+    fn attroff(&self, attributes: chtype) {
+        self.attributes.set(self.attributes.get() & !attributes);
+    }
+
+    fn mvprintw<T: AsRef<str>>(&self, y: i32, x: i32, text: T) {
+        for (i, ch) in text.as_ref().chars().enumerate() {
+            self.put(y, x + i as i32, ch);
+        }
+    }
+
+    fn mvhline(&self, y: i32, x: i32, ch: char, n: i32) {
+        for i in 0..n {
+            self.put(y, x + i, ch);
+        }
+    }
+
+    fn mvvline(&self, y: i32, x: i32, ch: char, n: i32) {
+        for i in 0..n {
+            self.put(y + i, x, ch);
+        }
+    }
+
+    fn clear(&self) {
+        // pick up any change to the terminal size
+        let size = self.size.get();
+        if size != self.buffer_size.get() {
+            let cell_count = (size.0 * size.1).max(0) as usize;
+            *self.shown.borrow_mut() = vec![BLANK; cell_count];
+            self.buffer_size.set(size);
+            self.needs_redraw.set(true);
+        }
+        let cell_count = self.shown.borrow().len();
+        *self.cells.borrow_mut() = vec![BLANK; cell_count];
+    }
+
+    fn refresh(&self) {
+        let mut output = String::new();
+        let mut shown = self.shown.borrow_mut();
+        if self.needs_redraw.replace(false) {
+            // hide the cursor and start over from a blank screen
+            output.push_str("\x1b[?25l\x1b[0m\x1b[2J");
+            *shown = vec![BLANK; shown.len()];
+        }
+
+        let columns = self.buffer_size.get().1;
+        let cells = self.cells.borrow();
+        let mut cursor = None;
+        let mut attributes = None;
+        for (index, (glyph, shown_glyph)) in cells.iter().zip(shown.iter()).enumerate() {
+            if glyph == shown_glyph {
+                continue;
+            }
+            let pos = (index as i32 / columns, index as i32 % columns);
+            if cursor != Some(pos) {
+                output.push_str(&format!("\x1b[{};{}H", pos.0 + 1, pos.1 + 1));
+            }
+            if attributes != Some(glyph.attributes) {
+                output.push_str(&select_graphic_rendition(glyph.attributes));
+                attributes = Some(glyph.attributes);
+            }
+            output.push(glyph.ch);
+            cursor = Some((pos.0, pos.1 + 1));
+        }
+        shown.copy_from_slice(&cells);
+
+        if !output.is_empty() {
+            self.write(&output);
+        }
+    }
+
+    fn lines(&self) -> i32 {
+        self.buffer_size.get().0
+    }
+
+    fn columns(&self) -> i32 {
+        self.buffer_size.get().1
+    }
+}
+
+/// The escape code that makes the terminal draw with `attributes`
+fn select_graphic_rendition(attributes: chtype) -> String {
+    let mut code = String::from("\x1b[0");
+    if attributes & attributes::A_REVERSE != 0 {
+        code.push_str(";7");
+    }
+    let color_pair = (attributes >> COLOR_SHIFT) & 0xff;
+    if color_pair >= FIRST_COLOR_PAIR {
+        code.push_str(&format!(";38;5;{}", color_pair));
+    }
+    code.push('m');
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_canvas(output: &mut Vec<u8>) -> AnsiCanvas<&mut Vec<u8>> {
+        AnsiCanvas::new(output, Rc::new(Cell::new((4, 10))))
+    }
+
+    #[test]
+    fn first_refresh_clears_the_screen_and_draws_text() {
+        let mut output = Vec::new();
+        let canvas = new_canvas(&mut output);
+        canvas.mvprintw(1, 2, "hi");
+        canvas.refresh();
+        drop(canvas);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\x1b[?25l\x1b[0m\x1b[2J"));
+        assert!(output.ends_with("\x1b[2;3H\x1b[0mhi"));
+    }
+
+    #[test]
+    fn only_changed_cells_are_sent() {
+        let mut output = Vec::new();
+        let canvas = new_canvas(&mut output);
+        canvas.mvprintw(0, 0, "abc");
+        canvas.refresh();
+        canvas.writer.borrow_mut().clear();
+
+        canvas.clear();
+        canvas.mvprintw(0, 0, "abd");
+        canvas.refresh();
+        drop(canvas);
+
+        assert_eq!(String::from_utf8(output).unwrap(), "\x1b[1;3H\x1b[0md");
+    }
+
+    #[test]
+    fn nothing_is_sent_when_nothing_changed() {
+        let mut output = Vec::new();
+        let canvas = new_canvas(&mut output);
+        canvas.mvprintw(0, 0, "abc");
+        canvas.refresh();
+        canvas.writer.borrow_mut().clear();
+
+        canvas.clear();
+        canvas.mvprintw(0, 0, "abc");
+        canvas.refresh();
+        drop(canvas);
+
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn drawing_outside_the_terminal_is_ignored() {
+        let mut output = Vec::new();
+        let canvas = new_canvas(&mut output);
+        canvas.mvprintw(-1, 0, "above");
+        canvas.mvprintw(0, 8, "edge");
+        canvas.mvvline(2, 0, '#', 10);
+        canvas.refresh();
+        drop(canvas);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains("above"));
+        assert!(output.contains("ed"));
+        assert!(!output.contains("edg"));
+        assert_eq!(output.matches('#').count(), 2);
+    }
+
+    #[test]
+    fn attributes_are_turned_into_colors() {
+        let reverse_green = attributes::A_REVERSE | (34 << COLOR_SHIFT);
+        assert_eq!(select_graphic_rendition(reverse_green), "\x1b[0;7;38;5;34m");
+        assert_eq!(select_graphic_rendition(1 << COLOR_SHIFT), "\x1b[0m");
+    }
+
+    #[test]
+    fn resized_terminal_is_redrawn_from_scratch() {
+        let mut output = Vec::new();
+        let size = Rc::new(Cell::new((4, 10)));
+        let canvas = AnsiCanvas::new(&mut output, size.clone());
+        canvas.refresh();
+        size.set((6, 20));
+        canvas.clear();
+        assert_eq!(canvas.lines(), 6);
+        assert_eq!(canvas.columns(), 20);
+        assert!(canvas.needs_redraw.get());
+    }
+}
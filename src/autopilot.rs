@@ -0,0 +1,276 @@
+use crate::ai;
+use crate::ai::Grid;
+use crate::rectilinear::Direction;
+use glam::i32;
+use glam::IVec2;
+use strum::IntoEnumIterator;
+
+/// Number of cells along the cycle that are kept free in front of the tail when
+/// taking shortcuts, so that the snake has some room to grow into
+const SHORTCUT_MARGIN: usize = 4;
+
+/// A closed path that visits every cell of a grid exactly once. A snake that
+/// only ever follows the cycle can never crash into itself, and will
+/// eventually fill up the whole grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HamiltonianCycle {
+    width: i32,
+    cells: Vec<IVec2>,     // the cells in the order they're visited
+    positions: Vec<usize>, // the position along the cycle of each cell
+}
+
+impl HamiltonianCycle {
+    /// Creates a cycle over a `width` by `height` grid. There is only a cycle
+    /// if at least one of the sides has an even length.
+    ///
+    /// The cycle goes along the top row, zigzags its way down through the
+    /// rest of the rows, and then returns up along the leftmost column:
+    ///
+    /// ```text
+    /// >>>>>v
+    /// ^v<<<<
+    /// ^>>>>v
+    /// ^<<<<<
+    /// ```
+    pub fn new(width: i32, height: i32) -> Option<Self> {
+        if width < 2 || height < 2 {
+            return None;
+        }
+        let cells = if height % 2 == 0 {
+            zigzag_cells(width, height)
+        } else if width % 2 == 0 {
+            // zigzag along the columns instead
+            zigzag_cells(height, width)
+                .into_iter()
+                .map(|cell| i32::ivec2(cell.y, cell.x))
+                .collect()
+        } else {
+            return None;
+        };
+
+        let mut positions = vec![0; cells.len()];
+        for (position, cell) in cells.iter().enumerate() {
+            positions[(cell.y * width + cell.x) as usize] = position;
+        }
+        Some(HamiltonianCycle {
+            width,
+            cells,
+            positions,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// How far along the cycle `cell` is
+    pub fn position(&self, cell: IVec2) -> usize {
+        self.positions[(cell.y * self.width + cell.x) as usize]
+    }
+
+    /// The cell that comes after `cell` in the cycle
+    pub fn next(&self, cell: IVec2) -> IVec2 {
+        self.cells[(self.position(cell) + 1) % self.len()]
+    }
+
+    /// The number of steps along the cycle it takes to get from `from` to `to`
+    pub fn distance(&self, from: IVec2, to: IVec2) -> usize {
+        let (from, to) = (self.position(from), self.position(to));
+        (to + self.len() - from) % self.len()
+    }
+}
+
+/// The cells of a cycle over a grid with an even `height`, as described in
+/// `HamiltonianCycle::new`.
+fn zigzag_cells(width: i32, height: i32) -> Vec<IVec2> {
+    let mut cells: Vec<IVec2> = (0..width).map(|x| i32::ivec2(x, 0)).collect();
+    for y in 1..height {
+        if y % 2 == 1 {
+            cells.extend((1..width).rev().map(|x| i32::ivec2(x, y)));
+        } else {
+            cells.extend((1..width).map(|x| i32::ivec2(x, y)));
+        }
+    }
+    cells.extend((1..height).rev().map(|y| i32::ivec2(0, y)));
+    cells
+}
+
+/// Decides which direction the autopilot should steer a snake in next.
+///
+/// The snake follows `cycle`, but takes shortcuts towards `target` while it's
+/// short enough for that to be safe. Shortcuts never jump past the `tail`, so
+/// the body always stays in the same order as the cycle. All cells occupied by
+/// snakes are expected to be blocked in `grid`. Returns `None` if there is
+/// nowhere to go.
+pub fn choose_direction(
+    cycle: &HamiltonianCycle,
+    grid: &Grid,
+    head: IVec2,
+    tail: IVec2,
+    snake_len: usize,
+    target: Option<IVec2>,
+) -> Option<Direction> {
+    // the tail moves out of the way at the same time as the head moves
+    let is_free = |cell: IVec2| grid.is_free(cell) || (cell == tail && grid.contains(cell));
+
+    let mut next = cycle.next(head);
+    if snake_len < cycle.len() / 2 && grid.contains(tail) {
+        let room = cycle.distance(head, tail).saturating_sub(SHORTCUT_MARGIN);
+        let wanted = target.map_or(1, |target| cycle.distance(head, target));
+        let max_skip = usize::min(room, wanted);
+
+        let mut best_skip = cycle.distance(head, next);
+        for dir in Direction::iter() {
+            let cell = head + dir.unit();
+            if !is_free(cell) {
+                continue;
+            }
+            let skip = cycle.distance(head, cell);
+            if skip <= max_skip && skip > best_skip {
+                next = cell;
+                best_skip = skip;
+            }
+        }
+    }
+
+    if is_free(next) {
+        return Direction::iter().find(|dir| head + dir.unit() == next);
+    }
+
+    // the body is out of order with the cycle, which can happen when the
+    // autopilot takes over from a player, so head for the most open space
+    // until the body is back in order
+    Direction::iter()
+        .filter(|dir| is_free(head + dir.unit()))
+        .max_by_key(|dir| ai::flood_fill(grid, head + dir.unit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items;
+    use rand::rngs::mock::StepRng;
+    use std::collections::VecDeque;
+
+    fn assert_is_cycle(cycle: &HamiltonianCycle, width: i32, height: i32) {
+        assert_eq!(cycle.len(), (width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let cell = i32::ivec2(x, y);
+                let next = cycle.next(cell);
+                let delta = next - cell;
+                assert_eq!(delta.x.abs() + delta.y.abs(), 1);
+                assert_eq!(cycle.distance(cell, next), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn cycle_visits_every_cell_once_with_even_height() {
+        let cycle = HamiltonianCycle::new(5, 4).unwrap();
+        assert_is_cycle(&cycle, 5, 4);
+    }
+
+    #[test]
+    fn cycle_visits_every_cell_once_with_even_width() {
+        let cycle = HamiltonianCycle::new(4, 5).unwrap();
+        assert_is_cycle(&cycle, 4, 5);
+    }
+
+    #[test]
+    fn there_is_no_cycle_with_only_odd_sides() {
+        assert_eq!(HamiltonianCycle::new(5, 3), None);
+        assert_eq!(HamiltonianCycle::new(1, 4), None);
+    }
+
+    #[test]
+    fn distance_wraps_around_the_cycle() {
+        let cycle = HamiltonianCycle::new(4, 4).unwrap();
+        let first = i32::ivec2(0, 0);
+        let last = i32::ivec2(0, 1);
+        assert_eq!(cycle.distance(first, last), 15);
+        assert_eq!(cycle.distance(last, first), 1);
+    }
+
+    #[test]
+    fn short_snake_takes_shortcut_towards_target() {
+        // snake moving right along the top row, with the target right below
+        let cycle = HamiltonianCycle::new(6, 6).unwrap();
+        let mut grid = Grid::new(6, 6);
+        grid.block(i32::ivec2(1, 0));
+        grid.block(i32::ivec2(2, 0));
+        let head = i32::ivec2(2, 0);
+        let tail = i32::ivec2(1, 0);
+        let target = Some(i32::ivec2(2, 4));
+
+        let dir = choose_direction(&cycle, &grid, head, tail, 2, target);
+        assert_eq!(dir, Some(Direction::Down));
+    }
+
+    #[test]
+    fn long_snake_follows_the_cycle() {
+        let cycle = HamiltonianCycle::new(4, 4).unwrap();
+        let mut grid = Grid::new(4, 4);
+        let body = [
+            (1, 0),
+            (2, 0),
+            (3, 0),
+            (3, 1),
+            (2, 1),
+            (1, 1),
+            (1, 2),
+            (2, 2),
+        ];
+        for &(x, y) in &body {
+            grid.block(i32::ivec2(x, y));
+        }
+        let head = i32::ivec2(2, 2);
+        let tail = i32::ivec2(1, 0);
+        let target = Some(i32::ivec2(0, 2));
+
+        // going down would be shorter, but the snake is too long for that
+        let dir = choose_direction(&cycle, &grid, head, tail, body.len(), target);
+        assert_eq!(dir, Some(Direction::Right));
+    }
+
+    /// Lets the autopilot play on a small board until the snake fills it up,
+    /// spawning apples the same way the game does when the board is nearly
+    /// full.
+    #[test]
+    fn autopilot_fills_the_whole_board() {
+        let (width, height) = (6, 5);
+        let cycle = HamiltonianCycle::new(width, height).unwrap();
+        let mut rng = StepRng::new(7, 13);
+        let mut body: VecDeque<IVec2> = VecDeque::from(vec![i32::ivec2(0, 1), i32::ivec2(0, 0)]);
+        let mut apple = Some(i32::ivec2(3, 3));
+
+        for _ in 0..10_000 {
+            let mut grid = Grid::new(width, height);
+            for &cell in &body {
+                grid.block(cell);
+            }
+            let head = body[0];
+            let tail = *body.back().unwrap();
+            let dir = choose_direction(&cycle, &grid, head, tail, body.len(), apple).unwrap();
+
+            let next = head + dir.unit();
+            assert!(grid.contains(next));
+            if Some(next) == apple {
+                body.push_front(next);
+                apple =
+                    items::pick_free_cell(&mut rng, width, height, |cell| !body.contains(&cell));
+            } else {
+                body.pop_back();
+                assert!(!body.contains(&next), "snake crashed into itself");
+                body.push_front(next);
+            }
+
+            if apple.is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(body.len(), cycle.len());
+        assert_eq!(apple, None);
+    }
+}
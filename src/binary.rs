@@ -0,0 +1,128 @@
+use crate::rectilinear::Direction;
+use std::io;
+use std::io::Read;
+
+/// Starting value of an FNV-1a hash
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Multiplied into an FNV-1a hash with every byte
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hashes numbers the same on every computer and with every version of Rust,
+/// unlike the hasher of the standard library, so that the hash can be saved
+/// or sent to another computer. It's FNV-1a over the little endian bytes of
+/// the numbers, with sizes hashed as 64 bit numbers.
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        StableHasher {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write(&[value]);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Reads exactly `N` bytes, failing if the reader runs out before that
+pub fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A direction, or the lack of one, as a single byte
+pub fn encode_direction(direction: Option<Direction>) -> u8 {
+    match direction {
+        None => 0,
+        Some(Direction::Right) => 1,
+        Some(Direction::Left) => 2,
+        Some(Direction::Up) => 3,
+        Some(Direction::Down) => 4,
+    }
+}
+
+pub fn decode_direction(byte: u8) -> io::Result<Option<Direction>> {
+    match byte {
+        0 => Ok(None),
+        1 => Ok(Some(Direction::Right)),
+        2 => Ok(Some(Direction::Left)),
+        3 => Ok(Some(Direction::Up)),
+        4 => Ok(Some(Direction::Down)),
+        _ => Err(invalid_data(format!("invalid direction {}", byte))),
+    }
+}
+
+pub fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn directions_survive_round_trip() {
+        let directions = [
+            None,
+            Some(Direction::Right),
+            Some(Direction::Left),
+            Some(Direction::Up),
+            Some(Direction::Down),
+        ];
+        for &direction in &directions {
+            assert_eq!(
+                decode_direction(encode_direction(direction)).unwrap(),
+                direction
+            );
+        }
+        assert!(decode_direction(5).is_err());
+    }
+
+    #[test]
+    fn hashes_match_fnv_1a() {
+        assert_eq!(StableHasher::new().finish(), 0xcbf2_9ce4_8422_2325);
+        let mut hasher = StableHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+        let mut hasher = StableHasher::new();
+        hasher.write_usize(1);
+        let mut expected = StableHasher::new();
+        expected.write(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(hasher.finish(), expected.finish());
+    }
+
+    #[test]
+    fn reading_past_the_end_is_an_error() {
+        let mut reader = Cursor::new(vec![1, 2, 3]);
+        assert_eq!(read_bytes::<_, 2>(&mut reader).unwrap(), [1, 2]);
+        assert!(read_bytes::<_, 2>(&mut reader).is_err());
+    }
+}
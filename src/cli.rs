@@ -0,0 +1,227 @@
+/// Number of games played by the benchmark unless told otherwise
+const DEFAULT_BENCHMARK_GAMES: usize = 10;
+
+/// The difficulties that can be given, in the order of the start menu
+const DIFFICULTIES: [&str; 3] = ["easy", "normal", "hard"];
+
+pub const USAGE: &str = "usage: curses_snake [--benchmark [games] | --host <port> | --join <address> | --serve <port> | --watch <address> | --replay [file]] [--broadcast <port>] [--seed <seed>] [--difficulty <easy|normal|hard>]";
+
+/// What the program has been asked to do on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Play the game as usual
+    Play,
+    /// Let the autopilot play a number of games without drawing anything
+    Benchmark(usize),
+    /// Wait for another player to join a network match on a port
+    Host(u16),
+    /// Join a network match hosted at an address, such as localhost:4000
+    Join(String),
+    /// Let anyone connecting with telnet on a port play a game of their own
+    Serve(u16),
+    /// Watch the game broadcast at an address, such as localhost:4001
+    Watch(String),
+    /// Play back a replay file, or the last round played if none is given
+    Replay(Option<String>),
+}
+
+/// Everything given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arguments {
+    pub command: Command,
+    /// Port on this computer to broadcast the game on, for others to watch
+    pub broadcast: Option<u16>,
+    /// Seed for the random number generator, to play the same game again
+    pub seed: Option<u64>,
+    /// Difficulty of a hosted network match, as its index in the start menu
+    pub difficulty: Option<u8>,
+}
+
+/// Parses the command line `args`, not including the program name
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Arguments, String> {
+    let mut args = args.peekable();
+    let mut command = None;
+    let mut broadcast = None;
+    let mut seed = None;
+    let mut difficulty = None;
+    while let Some(arg) = args.next() {
+        let next_command = match arg.as_str() {
+            "--benchmark" => {
+                let games = match args.next() {
+                    Some(games) => games
+                        .parse()
+                        .map_err(|_| format!("invalid number of games '{}'", games))?,
+                    None => DEFAULT_BENCHMARK_GAMES,
+                };
+                Command::Benchmark(games)
+            }
+            "--host" => Command::Host(parse_port(args.next().ok_or("--host needs a port")?)?),
+            "--join" => Command::Join(args.next().ok_or("--join needs an address")?),
+            "--serve" => Command::Serve(parse_port(args.next().ok_or("--serve needs a port")?)?),
+            "--watch" => Command::Watch(args.next().ok_or("--watch needs an address")?),
+            "--replay" => match args.peek() {
+                Some(file) if !file.starts_with("--") => Command::Replay(args.next()),
+                _ => Command::Replay(None),
+            },
+            "--broadcast" => {
+                broadcast = Some(parse_port(args.next().ok_or("--broadcast needs a port")?)?);
+                continue;
+            }
+            "--seed" => {
+                let text = args.next().ok_or("--seed needs a number")?;
+                seed = Some(
+                    text.parse()
+                        .map_err(|_| format!("invalid seed '{}'", text))?,
+                );
+                continue;
+            }
+            "--difficulty" => {
+                let name = args.next().ok_or("--difficulty needs a difficulty")?;
+                let index = DIFFICULTIES
+                    .iter()
+                    .position(|&difficulty| difficulty == name)
+                    .ok_or_else(|| format!("unknown difficulty '{}'", name))?;
+                difficulty = Some(index as u8);
+                continue;
+            }
+            _ if command.is_some() => return Err(format!("unexpected argument '{}'", arg)),
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        };
+        if command.replace(next_command).is_some() {
+            return Err(format!("unexpected argument '{}'", arg));
+        }
+    }
+
+    Ok(Arguments {
+        command: command.unwrap_or(Command::Play),
+        broadcast,
+        seed,
+        difficulty,
+    })
+}
+
+fn parse_port(port: String) -> Result<u16, String> {
+    port.parse().map_err(|_| format!("invalid port '{}'", port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string())).map(|arguments| arguments.command)
+    }
+
+    #[test]
+    fn no_arguments_plays_the_game() {
+        assert_eq!(parse(&[]), Ok(Command::Play));
+    }
+
+    #[test]
+    fn benchmark_takes_optional_number_of_games() {
+        assert_eq!(
+            parse(&["--benchmark"]),
+            Ok(Command::Benchmark(DEFAULT_BENCHMARK_GAMES))
+        );
+        assert_eq!(parse(&["--benchmark", "3"]), Ok(Command::Benchmark(3)));
+    }
+
+    #[test]
+    fn network_matches_are_hosted_on_port_and_joined_by_address() {
+        assert_eq!(parse(&["--host", "4000"]), Ok(Command::Host(4000)));
+        assert_eq!(
+            parse(&["--join", "localhost:4000"]),
+            Ok(Command::Join(String::from("localhost:4000")))
+        );
+    }
+
+    #[test]
+    fn telnet_server_is_served_on_port() {
+        assert_eq!(parse(&["--serve", "2323"]), Ok(Command::Serve(2323)));
+    }
+
+    #[test]
+    fn replay_takes_optional_file() {
+        assert_eq!(parse(&["--replay"]), Ok(Command::Replay(None)));
+        assert_eq!(
+            parse(&["--replay", "best.replay"]),
+            Ok(Command::Replay(Some(String::from("best.replay"))))
+        );
+        assert_eq!(
+            parse(&["--replay", "--seed", "1"]),
+            Ok(Command::Replay(None))
+        );
+    }
+
+    #[test]
+    fn broadcast_goes_along_with_other_commands() {
+        let parse_all = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            parse_all(&["--broadcast", "4001"]),
+            Ok(Arguments {
+                command: Command::Play,
+                broadcast: Some(4001),
+                seed: None,
+                difficulty: None,
+            })
+        );
+        assert_eq!(
+            parse_all(&["--host", "4000", "--broadcast", "4001"]),
+            Ok(Arguments {
+                command: Command::Host(4000),
+                broadcast: Some(4001),
+                seed: None,
+                difficulty: None,
+            })
+        );
+        assert_eq!(
+            parse(&["--watch", "localhost:4001"]),
+            Ok(Command::Watch(String::from("localhost:4001")))
+        );
+    }
+
+    #[test]
+    fn seed_goes_along_with_other_commands() {
+        let parse_all = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            parse_all(&["--seed", "1234", "--benchmark", "5"]),
+            Ok(Arguments {
+                command: Command::Benchmark(5),
+                broadcast: None,
+                seed: Some(1234),
+                difficulty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn difficulty_is_picked_by_name() {
+        let parse_all = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            parse_all(&["--host", "4000", "--difficulty", "hard"]),
+            Ok(Arguments {
+                command: Command::Host(4000),
+                broadcast: None,
+                seed: None,
+                difficulty: Some(2),
+            })
+        );
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        assert!(parse(&["--benchmark", "many"]).is_err());
+        assert!(parse(&["--fly"]).is_err());
+        assert!(parse(&["--benchmark", "3", "4"]).is_err());
+        assert!(parse(&["--host"]).is_err());
+        assert!(parse(&["--host", "99999"]).is_err());
+        assert!(parse(&["--join"]).is_err());
+        assert!(parse(&["--serve", "telnet"]).is_err());
+        assert!(parse(&["--host", "4000", "--join", "localhost:4000"]).is_err());
+        assert!(parse(&["--broadcast"]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--difficulty"]).is_err());
+        assert!(parse(&["--difficulty", "insane"]).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+/// Number of frames before an effect runs out that the player gets warned
+const EXPIRY_WARNING_FRAMES: usize = 2 * 60;
+
+/// Number of frames between each flash of an effect about to run out
+const EXPIRY_FLASH_PERIOD: usize = 6;
+
+/// Timed effects that can be applied to a snake
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Effect {
+    SpeedBoost,
+    Ghost,
+    SlowMotion,
+    Magnet,
+}
+
+impl Effect {
+    /// Number of frames the effect lasts
+    pub fn duration(&self) -> usize {
+        match self {
+            Effect::SpeedBoost => 5 * 60,
+            Effect::Ghost => 8 * 60,
+            Effect::SlowMotion => 8 * 60,
+            Effect::Magnet => 10 * 60,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::SpeedBoost => "speed",
+            Effect::Ghost => "ghost",
+            Effect::SlowMotion => "slow-mo",
+            Effect::Magnet => "magnet",
+        }
+    }
+
+    pub fn color(&self) -> u64 {
+        match self {
+            Effect::SpeedBoost => 51, // cyan
+            Effect::Ghost => 250,     // light grey
+            Effect::SlowMotion => 27, // blue
+            Effect::Magnet => 208,    // orange
+        }
+    }
+}
+
+/// An effect that is currently in play, along with how long it has left
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ActiveEffect {
+    pub effect: Effect,
+    pub frames_left: usize,
+}
+
+impl ActiveEffect {
+    pub fn seconds_left(&self) -> usize {
+        self.frames_left.div_ceil(60)
+    }
+
+    pub fn is_expiring(&self) -> bool {
+        self.frames_left < EXPIRY_WARNING_FRAMES
+    }
+
+    /// Alternates between true and false while the effect is about to expire,
+    /// and is false otherwise.
+    pub fn is_flashing(&self) -> bool {
+        self.is_expiring() && (self.frames_left / EXPIRY_FLASH_PERIOD) % 2 == 1
+    }
+}
+
+/// The list of effects currently applied to a snake, ordered from oldest to
+/// most recently applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveEffects {
+    effects: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    pub fn new() -> Self {
+        ActiveEffects {
+            effects: Vec::new(),
+        }
+    }
+
+    /// Applies `effect` for its full duration. If the effect is already
+    /// active, its duration is restarted instead of stacking another one.
+    pub fn add(&mut self, effect: Effect) {
+        self.effects.retain(|active| active.effect != effect);
+        self.effects.push(ActiveEffect {
+            effect,
+            frames_left: effect.duration(),
+        });
+    }
+
+    /// Counts down all effects by one frame, and removes the expired ones
+    pub fn tick(&mut self) {
+        for active in &mut self.effects {
+            active.frames_left = active.frames_left.saturating_sub(1);
+        }
+        self.effects.retain(|active| active.frames_left > 0);
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn is_active(&self, effect: Effect) -> bool {
+        self.effects.iter().any(|active| active.effect == effect)
+    }
+
+    pub fn frames_left(&self, effect: Effect) -> Option<usize> {
+        self.effects
+            .iter()
+            .find(|active| active.effect == effect)
+            .map(|active| active.frames_left)
+    }
+
+    /// The most recently applied effect, if any
+    pub fn latest(&self) -> Option<&ActiveEffect> {
+        self.effects.last()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActiveEffect> {
+        self.effects.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_effect_is_active() {
+        let mut effects = ActiveEffects::new();
+        effects.add(Effect::Ghost);
+        assert_eq!(effects.is_active(Effect::Ghost), true);
+        assert_eq!(effects.is_active(Effect::Magnet), false);
+    }
+
+    #[test]
+    fn effect_is_removed_after_its_duration() {
+        let mut effects = ActiveEffects::new();
+        effects.add(Effect::SlowMotion);
+        for _ in 0..Effect::SlowMotion.duration() - 1 {
+            effects.tick();
+        }
+        assert_eq!(effects.is_active(Effect::SlowMotion), true);
+        effects.tick();
+        assert_eq!(effects.is_active(Effect::SlowMotion), false);
+    }
+
+    #[test]
+    fn adding_active_effect_restarts_its_duration() {
+        let mut effects = ActiveEffects::new();
+        effects.add(Effect::Magnet);
+        effects.tick();
+        effects.add(Effect::Magnet);
+        assert_eq!(effects.iter().count(), 1);
+        assert_eq!(
+            effects.frames_left(Effect::Magnet),
+            Some(Effect::Magnet.duration())
+        );
+    }
+
+    #[test]
+    fn latest_effect_is_the_most_recently_added() {
+        let mut effects = ActiveEffects::new();
+        effects.add(Effect::Magnet);
+        effects.add(Effect::Ghost);
+        assert_eq!(effects.latest().unwrap().effect, Effect::Ghost);
+        effects.add(Effect::Magnet);
+        assert_eq!(effects.latest().unwrap().effect, Effect::Magnet);
+    }
+
+    #[test]
+    fn effect_only_flashes_when_about_to_expire() {
+        let mut active = ActiveEffect {
+            effect: Effect::Ghost,
+            frames_left: Effect::Ghost.duration(),
+        };
+        assert_eq!(active.is_expiring(), false);
+        assert_eq!(active.is_flashing(), false);
+
+        active.frames_left = EXPIRY_FLASH_PERIOD;
+        assert_eq!(active.is_expiring(), true);
+        assert_eq!(active.is_flashing(), true);
+    }
+}
@@ -1,23 +1,144 @@
 use glam::IVec2;
+use pancurses::chtype;
 use pancurses::Window;
 
 const BOX_CHAR: char = '█';
 
+/// Something the game can be drawn on, such as the curses window or the
+/// terminal of a player connected over the network. The methods mirror the
+/// curses functions of the same names.
+pub trait Canvas {
+    fn attron(&self, attributes: chtype);
+    fn attroff(&self, attributes: chtype);
+    fn mvprintw<T: AsRef<str>>(&self, y: i32, x: i32, text: T);
+    fn mvhline(&self, y: i32, x: i32, ch: char, n: i32);
+    fn mvvline(&self, y: i32, x: i32, ch: char, n: i32);
+    fn clear(&self);
+    fn refresh(&self);
+    fn lines(&self) -> i32;
+    fn columns(&self) -> i32;
+
+    fn top_screen_edge(&self) -> i32 {
+        (self.lines() - SCREEN_HEIGHT) / 2
+    }
+
+    fn left_screen_edge(&self) -> i32 {
+        (self.columns() - SCREEN_WIDTH) / 2
+    }
+
+    fn top_screen_margin(&self) -> i32 {
+        self.top_screen_edge() + (SCREEN_HEIGHT - BORDER_HEIGHT) / 2
+    }
+
+    fn left_screen_margin(&self) -> i32 {
+        self.left_screen_edge() + (SCREEN_WIDTH - BORDER_WIDTH) / 2
+    }
+
+    /// Returns the middle screen (x, y) coordinate
+    fn screen_middle(&self) -> (i32, i32) {
+        (
+            self.left_screen_margin() + BORDER_WIDTH / 2,
+            self.top_screen_margin() + BORDER_HEIGHT / 2,
+        )
+    }
+}
+
+impl Canvas for Window {
+    fn attron(&self, attributes: chtype) {
+        Window::attron(self, attributes);
+    }
+
+    fn attroff(&self, attributes: chtype) {
+        Window::attroff(self, attributes);
+    }
+
+    fn mvprintw<T: AsRef<str>>(&self, y: i32, x: i32, text: T) {
+        Window::mvprintw(self, y, x, text);
+    }
+
+    fn mvhline(&self, y: i32, x: i32, ch: char, n: i32) {
+        self.mv(y, x);
+        self.hline(ch, n);
+    }
+
+    fn mvvline(&self, y: i32, x: i32, ch: char, n: i32) {
+        self.mv(y, x);
+        self.vline(ch, n);
+    }
+
+    fn clear(&self) {
+        Window::clear(self);
+        // keep the cursor hidden and pick up any change to the terminal size
+        pancurses::curs_set(0);
+        pancurses::resize_term(0, 0);
+    }
+
+    fn refresh(&self) {
+        Window::refresh(self);
+    }
+
+    fn lines(&self) -> i32 {
+        unsafe { pancurses::LINES }
+    }
+
+    fn columns(&self) -> i32 {
+        unsafe { pancurses::COLS }
+    }
+}
+
+/// Lets functions that take `&impl Canvas` be passed a reference to a
+/// reference, the same way they could when taking a `&Window`
+impl<C: Canvas> Canvas for &C {
+    fn attron(&self, attributes: chtype) {
+        (*self).attron(attributes);
+    }
+
+    fn attroff(&self, attributes: chtype) {
+        (*self).attroff(attributes);
+    }
+
+    fn mvprintw<T: AsRef<str>>(&self, y: i32, x: i32, text: T) {
+        (*self).mvprintw(y, x, text);
+    }
+
+    fn mvhline(&self, y: i32, x: i32, ch: char, n: i32) {
+        (*self).mvhline(y, x, ch, n);
+    }
+
+    fn mvvline(&self, y: i32, x: i32, ch: char, n: i32) {
+        (*self).mvvline(y, x, ch, n);
+    }
+
+    fn clear(&self) {
+        (*self).clear();
+    }
+
+    fn refresh(&self) {
+        (*self).refresh();
+    }
+
+    fn lines(&self) -> i32 {
+        (*self).lines()
+    }
+
+    fn columns(&self) -> i32 {
+        (*self).columns()
+    }
+}
+
 pub trait WindowGraphics {
     fn draw_horizontal_line(&self, y: i32, x: i32, width: i32);
     fn draw_vertical_line(&self, y: i32, x: i32, height: i32);
     fn draw_line_segments(&self, line_segments: &Vec<IVec2>);
 }
 
-impl WindowGraphics for Window {
+impl<C: Canvas> WindowGraphics for C {
     fn draw_horizontal_line(&self, y: i32, x: i32, width: i32) {
-        self.mv(y, x);
-        self.hline(BOX_CHAR, width);
+        self.mvhline(y, x, BOX_CHAR, width);
     }
 
     fn draw_vertical_line(&self, y: i32, x: i32, height: i32) {
-        self.mv(y, x);
-        self.vline(BOX_CHAR, height);
+        self.mvvline(y, x, BOX_CHAR, height);
     }
 
     fn draw_line_segments(&self, line_segments: &Vec<IVec2>) {
@@ -54,35 +175,3 @@ pub const SCREEN_WIDTH: i32 = 84;
 pub const SCREEN_HEIGHT: i32 = 20;
 pub const BORDER_HEIGHT: i32 = 12;
 pub const BORDER_WIDTH: i32 = 5 * BORDER_HEIGHT;
-
-pub fn top_screen_edge() -> i32 {
-    ((term_lines() - SCREEN_HEIGHT) / 2) as i32
-}
-
-pub fn left_screen_edge() -> i32 {
-    ((term_columns() - SCREEN_WIDTH) / 2) as i32
-}
-
-pub fn top_screen_margin() -> i32 {
-    top_screen_edge() + (SCREEN_HEIGHT - BORDER_HEIGHT) / 2
-}
-
-pub fn left_screen_margin() -> i32 {
-    left_screen_edge() + (SCREEN_WIDTH - BORDER_WIDTH) / 2
-}
-
-/// Returns the middle screen (x, y) coordinate
-pub fn screen_middle() -> (i32, i32) {
-    (
-        left_screen_margin() + BORDER_WIDTH / 2,
-        top_screen_margin() + BORDER_HEIGHT / 2,
-    )
-}
-
-pub fn term_lines() -> i32 {
-    unsafe { pancurses::LINES }
-}
-
-pub fn term_columns() -> i32 {
-    unsafe { pancurses::COLS }
-}
@@ -0,0 +1,140 @@
+use crate::binary;
+use crate::storage;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = *b"SNHS";
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Number of scores kept in each table
+pub const TABLE_SIZE: usize = 10;
+
+/// Number of letters in the name of a high score
+pub const NAME_LENGTH: usize = 3;
+
+/// Which table a score goes in, as the index the difficulty and the mode it
+/// was played with have in the start menu
+pub type TableKey = (u8, u8);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: [u8; NAME_LENGTH], // in capital letters
+    pub score: u32,
+}
+
+/// The best scores for each difficulty and mode, from the highest down
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HighScores {
+    tables: BTreeMap<TableKey, Vec<HighScore>>,
+}
+
+impl HighScores {
+    pub fn table(&self, key: TableKey) -> &[HighScore] {
+        self.tables.get(&key).map_or(&[], |table| table.as_slice())
+    }
+
+    /// Whether `score` is good enough to make it into the table
+    pub fn qualifies(&self, key: TableKey, score: u32) -> bool {
+        let table = self.table(key);
+        score > 0 && (table.len() < TABLE_SIZE || table.iter().any(|entry| score > entry.score))
+    }
+
+    /// Puts `score` into its table below any equal scores already there, since
+    /// those were made first. Returns where it ended up, or `None` if it
+    /// didn't make it into the table.
+    pub fn insert(&mut self, key: TableKey, score: HighScore) -> Option<usize> {
+        if !self.qualifies(key, score.score) {
+            return None;
+        }
+        let table = self.tables.entry(key).or_default();
+        let rank = table.partition_point(|entry| entry.score >= score.score);
+        table.insert(rank, score);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        storage::write_versioned(&mut bytes, MAGIC, FORMAT_VERSION);
+        bytes.push(self.tables.len() as u8);
+        for (&(difficulty, mode), table) in &self.tables {
+            bytes.extend_from_slice(&[difficulty, mode, table.len() as u8]);
+            for entry in table {
+                bytes.extend_from_slice(&entry.name);
+                bytes.extend_from_slice(&entry.score.to_le_bytes());
+            }
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        storage::read_versioned(reader, MAGIC, FORMAT_VERSION, "high scores")?;
+        let mut high_scores = HighScores::default();
+        let [num_tables] = binary::read_bytes(reader)?;
+        for _ in 0..num_tables {
+            let [difficulty, mode, num_entries] = binary::read_bytes(reader)?;
+            let mut table = Vec::new();
+            for _ in 0..num_entries {
+                let name = binary::read_bytes(reader)?;
+                let score = u32::from_le_bytes(binary::read_bytes(reader)?);
+                table.push(HighScore { name, score });
+            }
+            high_scores.tables.insert((difficulty, mode), table);
+        }
+        Ok(high_scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn high_score(name: &[u8; NAME_LENGTH], score: u32) -> HighScore {
+        HighScore { name: *name, score }
+    }
+
+    #[test]
+    fn scores_are_kept_from_highest_to_lowest() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert((1, 0), high_score(b"AAA", 100)), Some(0));
+        assert_eq!(high_scores.insert((1, 0), high_score(b"BBB", 300)), Some(0));
+        assert_eq!(high_scores.insert((1, 0), high_score(b"CCC", 100)), Some(2));
+        let names: Vec<_> = high_scores
+            .table((1, 0))
+            .iter()
+            .map(|entry| &entry.name)
+            .collect();
+        assert_eq!(names, vec![b"BBB", b"AAA", b"CCC"]);
+        assert!(high_scores.table((0, 0)).is_empty());
+    }
+
+    #[test]
+    fn only_the_best_scores_are_kept() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=TABLE_SIZE as u32 {
+            high_scores.insert((0, 0), high_score(b"ABC", score * 10));
+        }
+        assert!(!high_scores.qualifies((0, 0), 10));
+        assert_eq!(high_scores.insert((0, 0), high_score(b"XYZ", 5)), None);
+        assert_eq!(high_scores.insert((0, 0), high_score(b"XYZ", 15)), Some(9));
+        assert_eq!(high_scores.table((0, 0)).len(), TABLE_SIZE);
+        assert_eq!(high_scores.table((0, 0))[9].score, 15);
+        assert!(!high_scores.qualifies((0, 1), 0));
+    }
+
+    #[test]
+    fn high_scores_survive_round_trip() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert((0, 1), high_score(b"ABC", 1234));
+        high_scores.insert((2, 3), high_score(b"XYZ", 56789));
+        let mut bytes = Vec::new();
+        high_scores.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            HighScores::read_from(&mut Cursor::new(bytes)).unwrap(),
+            high_scores
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use platform::keyboard;
+use platform::keyboard::KeyCode;
+use platform::keyboard::KeyboardHandler;
+use std::fmt::Debug;
+
+/// Where the keys pressed by a player come from, such as the keyboard of this
+/// computer or the terminal of a player connected over the network
+pub trait Keyboard: Debug {
+    /// Reads which keys have been pressed since the last frame
+    fn update(&mut self);
+
+    /// Whether `key` was pressed since the last frame
+    fn key_pressed_now(&self, key: KeyCode) -> bool;
+
+    fn any_key_pressed(&self) -> bool;
+}
+
+impl Keyboard for KeyboardHandler {
+    fn update(&mut self) {
+        KeyboardHandler::update(self);
+    }
+
+    fn key_pressed_now(&self, key: KeyCode) -> bool {
+        KeyboardHandler::key_pressed_now(self, key)
+    }
+
+    fn any_key_pressed(&self) -> bool {
+        keyboard::any_key_pressed(self)
+    }
+}
@@ -0,0 +1,252 @@
+use crate::effects::Effect;
+use enum_iterator::IntoEnumIterator;
+use glam::i32;
+use glam::IVec2;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Number of frames before an item expires that it starts blinking
+const EXPIRY_WARNING_FRAMES: usize = 2 * 60;
+
+/// Number of frames between each blink of an item about to expire
+const EXPIRY_BLINK_PERIOD: usize = 6;
+
+/// The different kinds of items that the snake can pick up
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
+pub enum Item {
+    Apple,
+    GoldenApple,
+    PoisonApple,
+    SpeedApple,
+    Ghost,
+    SlowMotion,
+    Magnet,
+}
+
+impl Item {
+    /// Points given for eating the item
+    pub fn points(&self) -> usize {
+        match self {
+            Item::Apple => 100,
+            Item::GoldenApple => 500,
+            Item::PoisonApple => 0,
+            Item::SpeedApple => 150,
+            Item::Ghost | Item::SlowMotion | Item::Magnet => 50,
+        }
+    }
+
+    /// Number of cells the snake grows by when eating the item
+    pub fn growth(&self) -> usize {
+        match self {
+            Item::Apple => 1,
+            Item::GoldenApple => 1,
+            Item::PoisonApple => 0,
+            Item::SpeedApple => 1,
+            Item::Ghost | Item::SlowMotion | Item::Magnet => 0,
+        }
+    }
+
+    /// Number of cells the snake shrinks by when eating the item
+    pub fn shrinkage(&self) -> usize {
+        match self {
+            Item::PoisonApple => 3,
+            _ => 0,
+        }
+    }
+
+    /// The effect applied to the snake when picking up the item, if any
+    pub fn effect(&self) -> Option<Effect> {
+        match self {
+            Item::SpeedApple => Some(Effect::SpeedBoost),
+            Item::Ghost => Some(Effect::Ghost),
+            Item::SlowMotion => Some(Effect::SlowMotion),
+            Item::Magnet => Some(Effect::Magnet),
+            _ => None,
+        }
+    }
+
+    /// Apples are the items that are pulled in by a magnet
+    pub fn is_apple(&self) -> bool {
+        matches!(self, Item::Apple | Item::GoldenApple | Item::SpeedApple)
+    }
+
+    pub fn color(&self) -> u64 {
+        match self {
+            Item::Apple => 88,        // red
+            Item::GoldenApple => 220, // gold
+            Item::PoisonApple => 93,  // purple
+            Item::SpeedApple => 51,   // cyan
+            Item::Ghost | Item::SlowMotion | Item::Magnet => {
+                self.effect().map(|effect| effect.color()).unwrap()
+            }
+        }
+    }
+
+    /// Relative chance of the item being picked when spawning a new item
+    pub fn spawn_weight(&self) -> usize {
+        match self {
+            Item::Apple => 64,
+            Item::GoldenApple => 8,
+            Item::PoisonApple => 10,
+            Item::SpeedApple => 6,
+            Item::Ghost => 4,
+            Item::SlowMotion => 4,
+            Item::Magnet => 4,
+        }
+    }
+
+    /// Number of frames the item stays on the board before it expires, or
+    /// `None` if it stays until eaten.
+    pub fn lifetime(&self) -> Option<usize> {
+        match self {
+            Item::Apple => None,
+            Item::GoldenApple => Some(6 * 60),
+            Item::PoisonApple => Some(10 * 60),
+            Item::SpeedApple => Some(8 * 60),
+            Item::Ghost | Item::SlowMotion | Item::Magnet => Some(8 * 60),
+        }
+    }
+}
+
+/// An item lying on the board at position `pos`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoardItem {
+    pub item: Item,
+    pub pos: IVec2,
+    pub frames_left: Option<usize>,
+}
+
+impl BoardItem {
+    pub fn new(item: Item, pos: IVec2) -> Self {
+        BoardItem {
+            item,
+            pos,
+            frames_left: item.lifetime(),
+        }
+    }
+
+    /// Count down the remaining lifetime of the item by one frame
+    pub fn tick(&mut self) {
+        if let Some(frames) = &mut self.frames_left {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+
+    pub fn has_expired(&self) -> bool {
+        self.frames_left == Some(0)
+    }
+
+    /// Items about to expire blink, so this is false every other blink period
+    /// during the last few seconds of the item lifetime.
+    pub fn is_visible(&self) -> bool {
+        match self.frames_left {
+            Some(frames) if frames < EXPIRY_WARNING_FRAMES => {
+                (frames / EXPIRY_BLINK_PERIOD).is_multiple_of(2)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Picks a random item, where each item is picked with a probability
+/// proportional to its spawn weight.
+pub fn pick_item<R: Rng>(rng: &mut R) -> Item {
+    let total_weight = Item::into_enum_iter().map(|item| item.spawn_weight()).sum();
+    item_from_roll(rng.gen_range(0..total_weight))
+}
+
+/// Picks a random cell among the free cells of a `width` by `height` area with
+/// its top left cell at (0, 0), or `None` if there are no free cells left.
+pub fn pick_free_cell<R, F>(rng: &mut R, width: i32, height: i32, is_free: F) -> Option<IVec2>
+where
+    R: Rng,
+    F: Fn(IVec2) -> bool,
+{
+    let free_cells: Vec<IVec2> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| i32::ivec2(x, y)))
+        .filter(|&cell| is_free(cell))
+        .collect();
+    free_cells.choose(rng).copied()
+}
+
+/// Maps a `roll` in the range [0, total spawn weight) onto an item
+fn item_from_roll(mut roll: usize) -> Item {
+    for item in Item::into_enum_iter() {
+        if roll < item.spawn_weight() {
+            return item;
+        }
+        roll -= item.spawn_weight();
+    }
+    Item::Apple
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn rolls_are_mapped_onto_items_by_spawn_weight() {
+        assert_eq!(item_from_roll(0), Item::Apple);
+        assert_eq!(item_from_roll(63), Item::Apple);
+        assert_eq!(item_from_roll(64), Item::GoldenApple);
+        assert_eq!(item_from_roll(72), Item::PoisonApple);
+        assert_eq!(item_from_roll(82), Item::SpeedApple);
+        assert_eq!(item_from_roll(88), Item::Ghost);
+        assert_eq!(item_from_roll(92), Item::SlowMotion);
+        assert_eq!(item_from_roll(96), Item::Magnet);
+        assert_eq!(item_from_roll(99), Item::Magnet);
+    }
+
+    #[test]
+    fn apples_never_expire() {
+        let mut apple = BoardItem::new(Item::Apple, i32::ivec2(0, 0));
+        for _ in 0..10_000 {
+            apple.tick();
+        }
+        assert_eq!(apple.has_expired(), false);
+        assert_eq!(apple.is_visible(), true);
+    }
+
+    #[test]
+    fn golden_apple_expires_after_its_lifetime() {
+        let mut golden = BoardItem::new(Item::GoldenApple, i32::ivec2(0, 0));
+        let lifetime = Item::GoldenApple.lifetime().unwrap();
+        for _ in 0..lifetime - 1 {
+            golden.tick();
+        }
+        assert_eq!(golden.has_expired(), false);
+        golden.tick();
+        assert_eq!(golden.has_expired(), true);
+    }
+
+    #[test]
+    fn item_blinks_when_about_to_expire() {
+        let mut golden = BoardItem::new(Item::GoldenApple, i32::ivec2(0, 0));
+        golden.frames_left = Some(EXPIRY_WARNING_FRAMES + 1);
+        assert_eq!(golden.is_visible(), true);
+
+        let visibility: Vec<bool> = (0..2 * EXPIRY_BLINK_PERIOD)
+            .map(|_| {
+                golden.tick();
+                golden.is_visible()
+            })
+            .collect();
+        assert!(visibility.contains(&true));
+        assert!(visibility.contains(&false));
+    }
+
+    #[test]
+    fn free_cell_is_picked_among_the_free_cells() {
+        let mut rng = StepRng::new(0, 1);
+        let only_free = i32::ivec2(2, 1);
+        let cell = pick_free_cell(&mut rng, 4, 3, |cell| cell == only_free);
+        assert_eq!(cell, Some(only_free));
+    }
+
+    #[test]
+    fn full_area_has_no_free_cell() {
+        let mut rng = StepRng::new(0, 1);
+        assert_eq!(pick_free_cell(&mut rng, 4, 3, |_| false), None);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::obstacles;
+use crate::obstacles::Motion;
+use crate::obstacles::Obstacle;
+use crate::rectilinear::Direction;
+use glam::i32;
+use glam::IVec2;
+
+/// The levels that come with the game, in the order they're listed in the
+/// start menu. The first level is the plain play area.
+const BUILTIN_LEVELS: [&str; 4] = [
+    include_str!("../levels/open.level"),
+    include_str!("../levels/warp.level"),
+    include_str!("../levels/crossroads.level"),
+    include_str!("../levels/machinery.level"),
+];
+
+/// Which way a snake heads after coming out of a portal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PortalExit {
+    Keep,            // carry on the way the snake went in
+    Turn(Direction), // always come out heading this way
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Portal {
+    pub pos: IVec2,
+    pub exit: PortalExit, // applies to snakes coming out of this portal
+}
+
+/// Anything placed on the play area besides the snakes and items
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub portals: Vec<[Portal; 2]>, // each portal leads to the other in its pair
+    pub obstacles: Vec<Obstacle>,  // as they are at the start of a round
+}
+
+impl Level {
+    /// The pair of portals that `pos` is a portal of, with the one at `pos` first
+    fn portal_pair(&self, pos: IVec2) -> Option<(&Portal, &Portal)> {
+        self.portals.iter().find_map(|[a, b]| {
+            if a.pos == pos {
+                Some((a, b))
+            } else if b.pos == pos {
+                Some((b, a))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn is_portal(&self, pos: IVec2) -> bool {
+        self.portal_pair(pos).is_some()
+    }
+
+    /// Where a snake heading in `dir` ends up after moving into `pos`, if
+    /// there's a portal at `pos`. The snake comes out next to the other portal
+    /// of the pair, heading the way that portal sends it.
+    pub fn teleport(&self, pos: IVec2, dir: Direction) -> Option<(IVec2, Direction)> {
+        let (_, exit) = self.portal_pair(pos)?;
+        let exit_dir = match exit.exit {
+            PortalExit::Keep => dir,
+            PortalExit::Turn(exit_dir) => exit_dir,
+        };
+        Some((exit.pos + exit_dir.unit(), exit_dir))
+    }
+}
+
+/// Number of levels that come with the game
+pub fn builtin_count() -> usize {
+    BUILTIN_LEVELS.len()
+}
+
+/// The level that comes with the game at `index`
+pub fn builtin(index: usize) -> Level {
+    parse(BUILTIN_LEVELS[index]).expect("built in levels are valid")
+}
+
+/// Parses the text of a level file. Each line holds one thing placed on the
+/// level, and anything after a `#` is a comment:
+///
+/// ```text
+/// name Warp
+/// # portal <x> <y> <exit> <x> <y> <exit>
+/// portal 1 5 keep 56 5 keep
+/// # slide <period> <x> <y> [<x> <y> ...]
+/// slide 8 10 6 20 6
+/// # bar <period> <x> <y> <arm>
+/// bar 20 40 6 2
+/// ```
+///
+/// The exit of a portal is `keep`, `up`, `down`, `left` or `right`. A sliding
+/// block moves in straight lines through the corners listed, going back to
+/// the first corner after the last, and a bar turns around the cell it's
+/// placed at. Both take a step every `period` frames.
+pub fn parse(text: &str) -> Result<Level, String> {
+    let mut name = None;
+    let mut portals: Vec<[Portal; 2]> = Vec::new();
+    let mut obstacles = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["name", rest @ ..] if !rest.is_empty() => name = Some(rest.join(" ")),
+            ["portal", x0, y0, exit0, x1, y1, exit1] => {
+                let pair = [
+                    parse_portal(x0, y0, exit0).map_err(|error| at_line(line_number, error))?,
+                    parse_portal(x1, y1, exit1).map_err(|error| at_line(line_number, error))?,
+                ];
+                let taken = |pos| {
+                    portals
+                        .iter()
+                        .flatten()
+                        .any(|portal: &Portal| portal.pos == pos)
+                };
+                if pair[0].pos == pair[1].pos || pair.iter().any(|portal| taken(portal.pos)) {
+                    return Err(at_line(
+                        line_number,
+                        "portals can't share a cell".to_string(),
+                    ));
+                }
+                portals.push(pair);
+            }
+            ["slide", period, corners @ ..] if !corners.is_empty() && corners.len() % 2 == 0 => {
+                let period = parse_period(period).map_err(|error| at_line(line_number, error))?;
+                let corners = corners
+                    .chunks(2)
+                    .map(|xy| {
+                        Ok(i32::ivec2(
+                            parse_coordinate(xy[0])?,
+                            parse_coordinate(xy[1])?,
+                        ))
+                    })
+                    .collect::<Result<Vec<IVec2>, String>>()
+                    .map_err(|error| at_line(line_number, error))?;
+                let path = obstacles::slide_path(&corners).ok_or_else(|| {
+                    at_line(
+                        line_number,
+                        "slides have to go in straight lines".to_string(),
+                    )
+                })?;
+                obstacles.push(Obstacle::new(Motion::Slide(path), period));
+            }
+            ["bar", period, x, y, arm] => {
+                let bar =
+                    parse_bar(period, x, y, arm).map_err(|error| at_line(line_number, error))?;
+                obstacles.push(bar);
+            }
+            [keyword, ..] => {
+                return Err(at_line(
+                    line_number,
+                    format!("can't make sense of '{}'", keyword),
+                ))
+            }
+        }
+    }
+    Ok(Level {
+        name: name.ok_or("level has no name")?,
+        portals,
+        obstacles,
+    })
+}
+
+fn at_line(line_number: usize, error: String) -> String {
+    format!("line {}: {}", line_number, error)
+}
+
+fn parse_portal(x: &str, y: &str, exit: &str) -> Result<Portal, String> {
+    let pos = i32::ivec2(parse_coordinate(x)?, parse_coordinate(y)?);
+    let exit = match exit {
+        "keep" => PortalExit::Keep,
+        "up" => PortalExit::Turn(Direction::Up),
+        "down" => PortalExit::Turn(Direction::Down),
+        "left" => PortalExit::Turn(Direction::Left),
+        "right" => PortalExit::Turn(Direction::Right),
+        _ => return Err(format!("invalid portal exit '{}'", exit)),
+    };
+    Ok(Portal { pos, exit })
+}
+
+fn parse_bar(period: &str, x: &str, y: &str, arm: &str) -> Result<Obstacle, String> {
+    let pivot = i32::ivec2(parse_coordinate(x)?, parse_coordinate(y)?);
+    let arm = arm
+        .parse()
+        .ok()
+        .filter(|&arm| arm > 0)
+        .ok_or(format!("invalid bar arm '{}'", arm))?;
+    Ok(Obstacle::new(
+        Motion::Rotate { pivot, arm },
+        parse_period(period)?,
+    ))
+}
+
+/// Frames between each step of an obstacle, which has to be at least one
+fn parse_period(text: &str) -> Result<usize, String> {
+    text.parse()
+        .ok()
+        .filter(|&period| period > 0)
+        .ok_or(format!("invalid period '{}'", text))
+}
+
+fn parse_coordinate(text: &str) -> Result<i32, String> {
+    text.parse()
+        .map_err(|_| format!("invalid coordinate '{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics;
+
+    #[test]
+    fn portals_are_read_in_pairs() {
+        let level = parse("name Test\nportal 1 2 keep 3 4 up # comment\n").unwrap();
+        assert_eq!(level.name, "Test");
+        assert_eq!(
+            level.portals,
+            vec![[
+                Portal {
+                    pos: i32::ivec2(1, 2),
+                    exit: PortalExit::Keep,
+                },
+                Portal {
+                    pos: i32::ivec2(3, 4),
+                    exit: PortalExit::Turn(Direction::Up),
+                },
+            ]]
+        );
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_line_number() {
+        assert_eq!(
+            parse("name Test\n\nportal 1 2 sideways 3 4 up"),
+            Err("line 3: invalid portal exit 'sideways'".to_string())
+        );
+        assert_eq!(
+            parse("name Test\nportal 1 2 keep 1 2 keep"),
+            Err("line 2: portals can't share a cell".to_string())
+        );
+        assert_eq!(
+            parse("name Test\nteleporter 1 2"),
+            Err("line 2: can't make sense of 'teleporter'".to_string())
+        );
+        assert!(parse("portal 1 2 keep 3 4 keep").is_err());
+    }
+
+    #[test]
+    fn obstacles_are_read_with_their_period() {
+        let level = parse("name Test\nslide 5 1 1 1 3\nbar 20 8 4 2").unwrap();
+        let path = vec![
+            i32::ivec2(1, 1),
+            i32::ivec2(1, 2),
+            i32::ivec2(1, 3),
+            i32::ivec2(1, 2),
+        ];
+        assert_eq!(
+            level.obstacles,
+            vec![
+                Obstacle::new(Motion::Slide(path), 5),
+                Obstacle::new(
+                    Motion::Rotate {
+                        pivot: i32::ivec2(8, 4),
+                        arm: 2,
+                    },
+                    20,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn obstacle_mistakes_are_reported() {
+        assert_eq!(
+            parse("name Test\nslide 5 1 1 3 3"),
+            Err("line 2: slides have to go in straight lines".to_string())
+        );
+        assert_eq!(
+            parse("name Test\nbar 0 1 1 2"),
+            Err("line 2: invalid period '0'".to_string())
+        );
+        assert_eq!(
+            parse("name Test\nbar 10 1 1 none"),
+            Err("line 2: invalid bar arm 'none'".to_string())
+        );
+        assert!(parse("name Test\nslide 5 1 1 3").is_err());
+    }
+
+    #[test]
+    fn snakes_come_out_next_to_the_other_portal() {
+        let level = parse("name Test\nportal 1 1 keep 8 8 left").unwrap();
+        assert_eq!(
+            level.teleport(i32::ivec2(1, 1), Direction::Down),
+            Some((i32::ivec2(7, 8), Direction::Left))
+        );
+        assert_eq!(
+            level.teleport(i32::ivec2(8, 8), Direction::Up),
+            Some((i32::ivec2(1, 0), Direction::Up))
+        );
+        assert_eq!(level.teleport(i32::ivec2(2, 2), Direction::Up), None);
+    }
+
+    #[test]
+    fn builtin_levels_are_valid() {
+        let width = graphics::BORDER_WIDTH - 2;
+        let height = graphics::BORDER_HEIGHT - 2;
+        let inside = |pos: IVec2| (0..width).contains(&pos.x) && (0..height).contains(&pos.y);
+        for text in BUILTIN_LEVELS.iter() {
+            let level = parse(text).unwrap();
+            for portal in level.portals.iter().flatten() {
+                assert!(inside(portal.pos), "{}: portal outside", level.name);
+                if let PortalExit::Turn(dir) = portal.exit {
+                    assert!(
+                        inside(portal.pos + dir.unit()),
+                        "{}: exit outside",
+                        level.name
+                    );
+                }
+            }
+            for obstacle in &level.obstacles {
+                let cells = obstacle.upcoming_cells(obstacle.poses());
+                assert!(
+                    cells.into_iter().all(inside),
+                    "{}: obstacle outside",
+                    level.name
+                );
+            }
+        }
+    }
+}
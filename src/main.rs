@@ -5,45 +5,141 @@
 // [ ] add a brief "good bye!" screen on exit
 // [ ] add pause menu
 // [ ] generic menu infrastructure (i.e. not hard coded menus)
-// [ ] fix bug where tail can extend into body when eating apples
+// [x] fix bug where tail can extend into body when eating apples
 
 mod graphics;
 #[macro_use]
 mod rectilinear;
+mod achievements;
+mod ai;
+mod ansi;
 mod attributes;
+mod autopilot;
+mod binary;
+mod cli;
+mod effects;
+mod high_scores;
+mod input;
+mod items;
+mod level;
 mod menu;
+mod net;
+mod obstacles;
+mod replay;
+mod save;
+mod scoring;
+mod snake_body;
+mod spectate;
+mod speed;
+mod stats;
+mod storage;
+mod survival;
+mod telnet;
+mod time_attack;
+mod tron;
+mod versus;
 
+use achievements::Achievement;
+use achievements::Unlocked;
+use ai::AiPersonality;
+use ansi::AnsiCanvas;
+use autopilot::HamiltonianCycle;
+use cli::Command;
+use effects::ActiveEffects;
+use effects::Effect;
 use enum_iterator::IntoEnumIterator;
 use glam::i32;
 use glam::IVec2;
+use graphics::Canvas;
 use graphics::WindowGraphics;
+use high_scores::HighScore;
+use high_scores::HighScores;
+use input::Keyboard;
+use items::BoardItem;
+use items::Item;
+use level::Level;
 use menu::ItemList;
+use net::Lockstep;
+use net::MatchSetup;
+use net::NetError;
+use obstacles::Obstacle;
 use pancurses;
 use platform;
+use platform::keyboard::KeyCode;
 use platform::keyboard::KeyboardHandler;
 use platform::virtual_keycodes;
 use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
 use rectilinear::ChainedLineSegment;
 use rectilinear::Direction;
 use rectilinear::RectilinearLine;
+use replay::Replay;
+use replay::ReplaySetup;
+use save::SavedRound;
+use scoring::ScoreBreakdown;
+use scoring::Scoring;
+use snake_body::SnakeBody;
+use spectate::Broadcast;
+use speed::SpeedCurve;
+use stats::DeathCause;
+use stats::RoundTally;
+use stats::Stats;
+use std::cell::Cell;
 use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use telnet::TelnetKeyboard;
+use versus::MatchScore;
 
 #[derive(Debug)]
 struct ProgramState {
     elapsed_frames: usize,
     quit_requested: bool,
-    keyboard_handler: KeyboardHandler,
+    keyboard_handler: Box<dyn Keyboard>,
     ivec2_gen: IVec2Generator,
+    lockstep: Option<Lockstep>, // connection to the other computer in a network match
+    local: bool,                // played at this computer rather than over telnet
+    unlocked: Unlocked,         // achievements
+    toasts: VecDeque<Toast>,    // announcing achievements as they're unlocked
     game_state: GameState,
 }
 
+/// A banner announcing a newly unlocked achievement, shown over whatever is
+/// on the screen for a while
+#[derive(Debug)]
+struct Toast {
+    achievement: &'static Achievement,
+    frames: usize, // the toast has been shown for
+}
+
 #[derive(Debug)]
 enum GameState {
     StartMenu(StartMenuState),
+    AttractMode(AttractModeState),
     RoundStart(RoundStartState),
     OngoingRound(RoundState),
+    Paused(RoundState),
     RoundEnd(RoundEndState),
+    RoundWinner(RoundWinnerState),
+    NameEntry(NameEntryState),
     GameOver(GameOverState),
+    Records(RecordsState),
+    HighScores(HighScoresState),
+    Statistics(StatisticsState),
+    Achievements(AchievementsState),
+    NetworkError(String),
+    Playback(Box<PlaybackState>),
     ProgramExit(usize), // frames
 }
 
@@ -51,23 +147,113 @@ enum GameState {
 struct StartMenuState {
     focused_area: StartMenuArea,
     menu_items: menu::ItemList<StartMenuItem>,
+    player_items: menu::ItemList<GamePlayers>,
+    rival_items: menu::ItemList<usize>,
     difficulty_items: menu::ItemList<GameDifficulty>,
-    difficulty: GameDifficulty,
+    mode_items: menu::ItemList<GameMode>,
+    level_items: menu::ItemList<usize>,
+    bite_items: menu::ItemList<BiteRule>,
+    ghost_items: menu::ItemList<bool>,
+    settings: GameSettings,
+    idle_frames: usize, // frames since any key was last pressed
+}
+
+/// The records kept between games, to pick one of them to look at from the
+/// start menu. The menu is kept around to return to it as it was.
+#[derive(Debug)]
+struct RecordsState {
+    menu: Box<StartMenuState>,
+    items: menu::ItemList<RecordsItem>,
+}
+
+/// The high scores of one difficulty and mode at a time, looked at from the
+/// records menu
+#[derive(Debug)]
+struct HighScoresState {
+    menu: Box<StartMenuState>,
+    high_scores: HighScores,
+    difficulty_items: menu::ItemList<GameDifficulty>,
+    mode_items: menu::ItemList<GameMode>,
+}
+
+/// The lifetime statistics, looked at a page at a time from the records menu
+#[derive(Debug)]
+struct StatisticsState {
+    menu: Box<StartMenuState>,
+    stats: Stats,
+    page: usize,
+}
+
+/// Every achievement, locked or not, looked at from the records menu
+#[derive(Debug)]
+struct AchievementsState {
+    menu: Box<StartMenuState>,
+}
+
+/// A demo round played by the computer, shown when the start menu has been
+/// left idle for a while. The menu is kept around to return to it as it was.
+#[derive(Debug)]
+struct AttractModeState {
+    menu: Box<StartMenuState>,
+    round: RoundState,
+    frames: usize,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum StartMenuArea {
     Main,
+    Players,
+    Rivals,
     Difficulty,
+    Mode,
+    Level,
+    Bites,
+    Ghost,
 }
 
-#[derive(Debug, Copy, Clone, IntoEnumIterator)]
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
 enum StartMenuItem {
+    Continue, // only there when a round has been saved
     Start,
+    Players,
+    Rivals,
     Difficulty,
+    Mode,
+    Level,
+    Bites,
+    Ghost,
+    Records,
     Exit,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
+enum RecordsItem {
+    HighScores,
+    Statistics,
+    Achievements,
+    Back,
+}
+
+/// The options chosen in the start menu for how to play the game
+#[derive(Debug, Copy, Clone)]
+struct GameSettings {
+    difficulty: GameDifficulty,
+    mode: GameMode,
+    level: usize, // which of the built in levels to play on
+    bite_rule: BiteRule,
+    ghost: bool, // race against the best single player round so far
+    players: GamePlayers,
+    rivals: usize,                 // computer controlled snakes
+    network_player: Option<usize>, // the player on this computer in a network match
+    seed: Option<u64>,             // to play the same game every time
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
+enum GamePlayers {
+    One,
+    Two,
+}
+
 #[derive(Debug, Copy, Clone, IntoEnumIterator)]
 enum GameDifficulty {
     Easy,
@@ -75,6 +261,22 @@ enum GameDifficulty {
     Hard,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
+enum GameMode {
+    Classic,
+    TimeAttack, // race the clock, respawning on death
+    Survival,   // the walls close in over time
+    Tron,       // snakes never stop growing, and there are no items
+}
+
+/// What happens when a snake bites itself
+#[derive(Debug, Copy, Clone, PartialEq, IntoEnumIterator)]
+enum BiteRule {
+    Crash,  // the snake crashes, as with anything else it runs into
+    CutOff, // the part behind the bite falls off, costing points
+    Debris, // as with cutting off, but the part is left lying in the way
+}
+
 #[derive(Debug, PartialEq)]
 enum QuitRequested {
     Yes,
@@ -87,38 +289,238 @@ enum ExitMenu {
     No,
 }
 
+/// A recorded round being played back, either on its own or as the ghost of
+/// a round being played
+#[derive(Debug)]
+struct PlaybackState {
+    replay: Replay,
+    round: RoundState,
+    generator: IVec2Generator, // kept apart from any round being played
+    frame: u32,                // of the replay, that the round has been played up to
+    speed: usize, // index into the playback speeds
+    paused: bool,
+    progress: f32, // frames accumulated towards the next frame of the replay
+}
+
 #[derive(Debug)]
 struct RoundStartState {
     frames: usize,
-    difficulty: GameDifficulty,
+    settings: GameSettings,
+    match_score: Option<MatchScore>,
+    new_game: bool, // as opposed to the next round of a match
 }
 
 #[derive(Debug)]
 struct RoundState {
-    snake: SnakeState,
-    apple: IVec2,
+    snakes: Vec<SnakeState>,
+    item: BoardItem,
     wall: RectilinearLine,
-    score: usize,
     game_over: bool,
-    difficulty: GameDifficulty,
+    board_cleared: bool, // the snakes have filled up every cell
+    settings: GameSettings,
+    match_score: Option<MatchScore>,
+    clock: Option<time_attack::Clock>, // counts down in time attack rounds
+    arena: Option<survival::Arena>,    // closes in during survival rounds
+    trails: Option<tron::TrailGrid>,   // covered cells in tron rounds
+    level: Level,
+    obstacles: Vec<Obstacle>, // moving along their paths
+    seed: u64,                // the game was started with
+    replay: Option<Replay>,   // of what the players have done, except in demo rounds
+    ghost: Option<Box<PlaybackState>>, // the best round so far, played alongside
 }
 
 impl RoundState {
-    fn new(generator: &mut IVec2Generator, difficulty: GameDifficulty) -> Self {
-        let mut snake = SnakeState::new();
-        snake.movement_period = match difficulty {
-            GameDifficulty::Easy => 8,
-            GameDifficulty::Normal => 6,
-            GameDifficulty::Hard => 4,
+    fn new(
+        generator: &mut IVec2Generator,
+        settings: GameSettings,
+        match_score: Option<MatchScore>,
+    ) -> Self {
+        // every round gets a seed of its own, so that it can be played back
+        // without the rounds that came before it
+        let seed = generator.rng.gen();
+        let mut round = RoundState::with_seed(generator, seed, settings, match_score);
+        if settings.ghost && settings.is_single_player() {
+            round.ghost = load_ghost(&settings).map(Box::new);
+        }
+        round
+    }
+
+    /// Creates a round where everything random follows from `seed`, which is
+    /// recorded in the replay of the round
+    fn with_seed(
+        generator: &mut IVec2Generator,
+        seed: u64,
+        settings: GameSettings,
+        match_score: Option<MatchScore>,
+    ) -> Self {
+        generator.rng = StdRng::seed_from_u64(seed);
+        let player_controllers = match (settings.players, settings.network_player) {
+            (_, Some(local_player)) => (0..2)
+                .map(|player| {
+                    if player == local_player {
+                        Controller::Keyboard(ARROW_KEYS)
+                    } else {
+                        Controller::Network
+                    }
+                })
+                .collect(),
+            (GamePlayers::One, None) => vec![Controller::Keyboard(ARROW_KEYS)],
+            (GamePlayers::Two, None) => vec![
+                Controller::Keyboard(WASD_KEYS),
+                Controller::Keyboard(ARROW_KEYS),
+            ],
+        };
+        let rival_controllers =
+            vec![Controller::Ai(settings.difficulty.ai_personality()); settings.rivals];
+        let controllers = player_controllers
+            .into_iter()
+            .chain(rival_controllers)
+            .collect();
+        let mut round = RoundState::with_controllers(generator, controllers, settings, match_score);
+        round.replay = Some(Replay::new(replay_setup(settings, seed)));
+        round
+    }
+
+    /// Creates a demo round where the computer plays by itself
+    fn new_demo(generator: &mut IVec2Generator, settings: GameSettings) -> Self {
+        let controllers = vec![Controller::Ai(AiPersonality::Hard)];
+        let settings = GameSettings {
+            mode: GameMode::Classic,
+            ..settings
+        };
+        RoundState::with_controllers(generator, controllers, settings, None)
+    }
+
+    /// Creates a round with one snake per controller, where the snakes of the
+    /// players are expected to come before the computer controlled ones.
+    fn with_controllers(
+        generator: &mut IVec2Generator,
+        controllers: Vec<Controller>,
+        settings: GameSettings,
+        match_score: Option<MatchScore>,
+    ) -> Self {
+        let difficulty = settings.difficulty;
+        // the computer controlled snakes get their own colors
+        let num_players = controllers
+            .iter()
+            .filter(|controller| !controller.is_ai())
+            .count();
+        let colors = PLAYER_COLORS[..num_players]
+            .iter()
+            .chain(RIVAL_COLORS.iter());
+
+        // spread the snakes out evenly along the top of the play area
+        let play_area_width = graphics::BORDER_WIDTH - 2;
+        let num_snakes = controllers.len();
+        let mut snakes: Vec<SnakeState> = controllers
+            .into_iter()
+            .zip(colors)
+            .enumerate()
+            .map(|(index, (controller, &color))| {
+                let x = play_area_width * (index as i32 + 1) / (num_snakes as i32 + 1);
+                SnakeState::new(x, color, controller, difficulty)
+            })
+            .collect();
+        let trails = match settings.mode {
+            GameMode::Tron => {
+                let mut trails = tron::TrailGrid::new(
+                    graphics::BORDER_WIDTH - 2,
+                    graphics::BORDER_HEIGHT - 2,
+                );
+                for (index, snake) in snakes.iter_mut().enumerate() {
+                    for run in &snake.body.runs {
+                        trails.cover_line(run, index);
+                    }
+                    // the trail grows all the time, so keep the speed steady
+                    snake.speed_curve.min_period = snake.speed_curve.initial_period;
+                }
+                Some(trails)
+            }
+            _ => None,
         };
-        let apple = generate_apple(generator, &snake.body);
+        let clock = match settings.mode {
+            GameMode::TimeAttack => Some(time_attack::Clock::new(time_attack::ROUND_FRAMES)),
+            _ => None,
+        };
+        let arena = match settings.mode {
+            GameMode::Survival => Some(survival::Arena::new(
+                graphics::BORDER_WIDTH - 2,
+                graphics::BORDER_HEIGHT - 2,
+            )),
+            _ => None,
+        };
+        let level = level::builtin(settings.level);
+        let obstacles = level.obstacles.clone();
+        let is_open = |point| is_open_for_item(point, arena.as_ref(), &level, &obstacles);
+        let item = generate_item(generator, &snakes, is_open)
+            .expect("new round has room for an item");
         RoundState {
-            snake,
-            apple,
-            wall: new_play_area_wall(),
-            score: 0,
+            snakes,
+            item,
+            wall: new_play_area_wall(0),
             game_over: false,
-            difficulty,
+            board_cleared: false,
+            settings,
+            match_score,
+            clock,
+            arena,
+            trails,
+            level,
+            obstacles,
+            seed: generator.seed,
+            replay: None,
+            ghost: None,
+        }
+    }
+
+    /// A round with a single player is over when the player dies, and a round
+    /// between several players is over when at most one player is left alive.
+    /// Computer controlled snakes don't count towards when the round is over,
+    /// except in demo rounds without players, which are over when all the
+    /// snakes are dead. A round is also over once the board has been cleared.
+    /// Time attack rounds are only over once the clock runs out, and a single
+    /// player tron round is also over once the player has outlasted the rivals.
+    fn is_over(&self) -> bool {
+        if self.board_cleared {
+            return true;
+        }
+        if let Some(clock) = &self.clock {
+            return clock.is_up();
+        }
+        let players = self.snakes.iter().filter(|snake| !snake.is_ai());
+        let (total, alive) = players.fold((0, 0), |(total, alive), snake| {
+            (total + 1, alive + snake.alive as usize)
+        });
+        match total {
+            0 => self.snakes.iter().all(|snake| !snake.alive),
+            1 if self.trails.is_some() => alive == 0 || self.rivals_alive() == 0,
+            1 => alive == 0,
+            _ => alive <= 1,
+        }
+    }
+
+    fn rivals_alive(&self) -> usize {
+        self.snakes
+            .iter()
+            .filter(|snake| snake.alive && snake.is_ai())
+            .count()
+    }
+
+    /// Whether there are items on the board, which there aren't in tron rounds
+    fn has_items(&self) -> bool {
+        self.trails.is_none()
+    }
+
+    /// The player left alive at the end of a round, or `None` if it was a draw
+    fn winner(&self) -> Option<usize> {
+        let mut alive = self
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|(_, snake)| snake.alive && !snake.is_ai());
+        match (alive.next(), alive.next()) {
+            (Some((index, _)), None) => Some(index),
+            _ => None,
         }
     }
 }
@@ -129,66 +531,571 @@ struct RoundEndState {
     frames: usize,
 }
 
+/// Shows who won the last round of a versus match, and who won the match once
+/// a player has won enough rounds.
+#[derive(Debug)]
+struct RoundWinnerState {
+    winner: Option<usize>,
+    match_score: MatchScore,
+    settings: GameSettings,
+    selection: GameOverSelection,
+    frames: usize,
+}
+
 #[derive(Debug)]
 struct SnakeState {
-    movement_period: usize,
-    body: RectilinearLine,
+    speed_curve: SpeedCurve,
+    body: SnakeBody,
     direction: Direction,
     color: u64,
-    movement_frames: usize,
+    player_color: u64,
+    movement_progress: f32, // frames accumulated towards next movement step
+    pending_growth: usize,  // moves left for which the tail stays where it is
     turn_cooldown: usize,
+    straight_frames: usize,  // since the snake last turned
+    longest_straight: usize, // the most straight frames in the round so far
+    effects: ActiveEffects,
+    controller: Controller,
+    autopilot: bool,      // steered by the autopilot instead of its controller
+    used_autopilot: bool, // at any point in the round
+    scoring: Scoring,
+    tally: RoundTally, // for the lifetime statistics
+    alive: bool,
+}
+
+/// What decides which way a snake goes
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Controller {
+    Keyboard(KeyBindings),
+    Ai(AiPersonality),
+    Network, // the player on the other computer in a network match
+}
+
+impl Controller {
+    fn is_ai(&self) -> bool {
+        match self {
+            Controller::Ai(_) => true,
+            Controller::Keyboard(_) | Controller::Network => false,
+        }
+    }
+}
+
+/// The keys used to steer a snake
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct KeyBindings {
+    up: KeyCode,
+    down: KeyCode,
+    left: KeyCode,
+    right: KeyCode,
 }
 
+const ARROW_KEYS: KeyBindings = KeyBindings {
+    up: virtual_keycodes::VK_UP,
+    down: virtual_keycodes::VK_DOWN,
+    left: virtual_keycodes::VK_LEFT,
+    right: virtual_keycodes::VK_RIGHT,
+};
+
+const WASD_KEYS: KeyBindings = KeyBindings {
+    up: virtual_keycodes::VK_W,
+    down: virtual_keycodes::VK_S,
+    left: virtual_keycodes::VK_A,
+    right: virtual_keycodes::VK_D,
+};
+
+/// The colors of player one and player two
+const PLAYER_COLORS: [u64; 2] = [34, 39]; // green, blue
+
+/// The colors of the computer controlled snakes
+const RIVAL_COLORS: [u64; MAX_RIVALS] = [166, 129, 244]; // brown, violet, grey
+
+/// The colors of the portal pairs, in the order they're listed in the level
+const PORTAL_COLORS: [u64; 3] = [51, 208, 201]; // cyan, orange, pink
+
+/// The most computer controlled snakes that can be picked in the start menu
+const MAX_RIVALS: usize = 3;
+
+/// Number of frames the start menu can sit idle before attract mode starts
+const ATTRACT_MODE_DELAY: usize = 20 * 60;
+
+/// The color of the moving obstacles
+const OBSTACLE_COLOR: u64 = 136; // dark yellow
+
+/// Items aren't placed in cells that an obstacle passes through within this
+/// many steps, so that they don't get covered up right away
+const OBSTACLE_ITEM_CLEARANCE: usize = 3;
+
+/// Number of random cells tried when placing an item before falling back to
+/// picking among all the free cells, which is slower but works on a full board
+const ITEM_PLACEMENT_ATTEMPTS: usize = 32;
+
+/// Key that toggles the autopilot during single player rounds
+const AUTOPILOT_KEY: KeyCode = virtual_keycodes::VK_F1;
+
+/// The benchmark gives up on a game that goes on for longer than this
+const BENCHMARK_MAX_FRAMES: usize = 10_000_000;
+
+/// Number of frames the winner of a round in a network match is shown before
+/// the next round starts by itself
+const NETWORK_ROUND_WINNER_FRAMES: usize = 3 * 60;
+
+/// Number of frames between each blink of the attract mode banner
+const ATTRACT_BANNER_BLINK_PERIOD: usize = 40;
+
+/// Number of round wins needed to win a versus match
+const MATCH_WINS_NEEDED: usize = 3;
+
 #[derive(Debug)]
 struct GameOverState {
+    board_cleared: bool,
+    outlasted_rivals: bool, // won a single player tron round
+    clock: Option<time_attack::Clock>, // how the time attack round went
     final_score: usize,
-    difficulty: GameDifficulty,
+    breakdown: ScoreBreakdown,
+    settings: GameSettings,
+    seed: u64,                // to play the same game again
+    high_score: Option<usize>, // where the score ended up in the high scores
     selection: GameOverSelection,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The player picking the letters of their name one at a time, arcade style,
+/// for a score that made it into the high scores
+#[derive(Debug)]
+struct NameEntryState {
+    game_over: GameOverState,
+    name: [u8; high_scores::NAME_LENGTH], // in capital letters
+    cursor: usize,                         // the letter being picked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum GameOverSelection {
     Restart,
     Exit,
 }
 
-/// Used for generating the position of the apples
+/// Used for generating the position of the items, and everything else in the
+/// game that's random. Every game starts by seeding it, so that the same seed
+/// and the same inputs always play out the same game.
 #[derive(Debug)]
 struct IVec2Generator {
-    rng: rand::prelude::ThreadRng,
+    rng: StdRng,
+    seed: u64, // the generator was last seeded with
     x_dist: Uniform<i32>,
     y_dist: Uniform<i32>,
 }
 
 impl IVec2Generator {
+    fn new(seed: u64) -> Self {
+        IVec2Generator {
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            x_dist: Uniform::from(1..graphics::BORDER_WIDTH - 3),
+            y_dist: Uniform::from(1..graphics::BORDER_HEIGHT - 3),
+        }
+    }
+
+    /// Seeds the generator for a new game, with `seed` if there is one or with
+    /// a new random seed otherwise
+    fn start_game(&mut self, seed: Option<u64>) {
+        self.seed = seed.unwrap_or_else(rand::random);
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+
     fn gen_ivec2(&mut self) -> IVec2 {
         i32::ivec2(
             self.x_dist.sample(&mut self.rng),
             self.y_dist.sample(&mut self.rng),
         )
     }
+
+    fn gen_item(&mut self) -> Item {
+        items::pick_item(&mut self.rng)
+    }
+}
+
+/// The length of the snake at the start of a round
+const SNAKE_START_LENGTH: usize = 4;
+
+/// The length of the snake when it respawns in a time attack round
+const SNAKE_RESPAWN_LENGTH: usize = 3;
+
+/// Number of cells ahead of a respawned snake that are kept clear, giving the
+/// player time to steer before running into anything
+const RESPAWN_CLEARANCE: usize = 3;
+
+/// The clock turns red when there are this many seconds left of a time attack
+const CLOCK_WARNING_SECONDS: usize = 10;
+
+/// Points taken off for every cell a snake loses by biting itself
+const BITE_PENALTY_PER_CELL: usize = 20;
+
+/// Name of the file the replay of the last round played is saved to, in the
+/// replay folder
+const LAST_REPLAY_FILE: &str = "last.replay";
+
+/// Name of the file an unfinished round is saved to, in the data folder
+const SAVED_ROUND_FILE: &str = "saved.round";
+
+/// Name of the file the high scores are saved to, in the data folder
+const HIGH_SCORES_FILE: &str = "high_scores";
+
+/// Name of the file the lifetime statistics are saved to, in the data folder
+const STATS_FILE: &str = "statistics";
+
+/// Name of the file the unlocked achievements are saved to, in the data folder
+const ACHIEVEMENTS_FILE: &str = "achievements";
+
+/// Number of frames each achievement toast is shown for
+const TOAST_FRAMES: usize = 3 * 60;
+
+/// The key that pauses and unpauses a round
+const PAUSE_KEY: KeyCode = virtual_keycodes::VK_P;
+
+/// The speeds a replay can be played back at, picked with the number keys
+const PLAYBACK_SPEEDS: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+
+/// Number of frames a replay skips backwards or forwards when seeking
+const PLAYBACK_SEEK_FRAMES: u32 = 5 * 60;
+
+/// Number of frames the debris of a snake that bit itself stays in the way
+const DEBRIS_FRAMES: usize = 8 * 60;
+
+/// The color of high score titles
+const HIGH_SCORE_COLOR: u64 = 220; // yellow
+
+/// The color of the ghost of the best round so far
+const GHOST_COLOR: u64 = 244; // grey
+
+/// The color of the debris left behind by snakes
+const DEBRIS_COLOR: u64 = 240; // dark grey
+
+/// Points awarded to the players still riding when a tron rival crashes
+const TRON_CRASH_POINTS: usize = 500;
+
+/// The snake can never be shrunk shorter than this
+const SNAKE_MIN_LENGTH: usize = 2;
+
+/// Apples within this distance of the head get pulled in by a magnet
+const MAGNET_RADIUS: i32 = 8;
+
+/// Number of frames between each step a magnet pulls an apple
+const MAGNET_PULL_PERIOD: usize = 8;
+
+impl GameDifficulty {
+    /// How cleverly the computer controlled snakes play
+    fn ai_personality(&self) -> AiPersonality {
+        match self {
+            GameDifficulty::Easy => AiPersonality::Easy,
+            GameDifficulty::Normal => AiPersonality::Normal,
+            GameDifficulty::Hard => AiPersonality::Hard,
+        }
+    }
+
+    /// How much points are scaled by, in percent
+    fn score_percent(&self) -> usize {
+        match self {
+            GameDifficulty::Easy => 100,
+            GameDifficulty::Normal => 150,
+            GameDifficulty::Hard => 200,
+        }
+    }
+
+    /// How fast the snake moves, and how quickly it speeds up as it grows
+    fn speed_curve(&self) -> SpeedCurve {
+        match self {
+            GameDifficulty::Easy => SpeedCurve {
+                initial_period: 8.0,
+                min_period: 5.0,
+                period_step: 0.25,
+                growth_per_level: 3,
+            },
+            GameDifficulty::Normal => SpeedCurve {
+                initial_period: 6.0,
+                min_period: 3.5,
+                period_step: 0.25,
+                growth_per_level: 2,
+            },
+            GameDifficulty::Hard => SpeedCurve {
+                initial_period: 4.0,
+                min_period: 2.0,
+                period_step: 0.25,
+                growth_per_level: 2,
+            },
+        }
+    }
+}
+
+impl PlaybackState {
+    /// Gets `replay` of a round played with `settings` ready to be played
+    /// back from the start
+    fn new(replay: Replay, settings: GameSettings) -> Self {
+        let seed = replay.setup.seed;
+        let mut generator = IVec2Generator::new(seed);
+        let round = RoundState::with_seed(&mut generator, seed, settings, None);
+        PlaybackState {
+            replay,
+            round,
+            generator,
+            frame: 0,
+            speed: 1, // normal speed
+            paused: false,
+            progress: 0.0,
+        }
+    }
+}
+
+impl StartMenuState {
+    fn new(seed: Option<u64>, can_continue: bool) -> Self {
+        StartMenuState {
+            focused_area: StartMenuArea::Main,
+            menu_items: start_menu_items(can_continue),
+            player_items: ItemList::new(GamePlayers::into_enum_iter(), 0),
+            rival_items: ItemList::new(0..=MAX_RIVALS, 0),
+            difficulty_items: ItemList::new(GameDifficulty::into_enum_iter(), 1),
+            mode_items: ItemList::new(GameMode::into_enum_iter(), 0),
+            level_items: ItemList::new(0..level::builtin_count(), 0),
+            bite_items: ItemList::new(BiteRule::into_enum_iter(), 0),
+            ghost_items: ItemList::new([false, true].iter().copied(), 0),
+            settings: GameSettings {
+                difficulty: GameDifficulty::Normal,
+                mode: GameMode::Classic,
+                level: 0,
+                bite_rule: BiteRule::Crash,
+                ghost: false,
+                players: GamePlayers::One,
+                rivals: 0,
+                network_player: None,
+                seed,
+            },
+            idle_frames: 0,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Whether a single player is playing alone on this computer
+    fn is_single_player(&self) -> bool {
+        self.players == GamePlayers::One && self.network_player.is_none()
+    }
+
+    /// The settings actually played with, since time attack is a single
+    /// player mode regardless of the players chosen in the menu, and a single
+    /// player needs at least one rival to play tron against.
+    fn for_mode(self) -> Self {
+        match self.mode {
+            GameMode::TimeAttack => GameSettings {
+                players: GamePlayers::One,
+                ..self
+            },
+            GameMode::Tron if self.players == GamePlayers::One => GameSettings {
+                rivals: usize::max(self.rivals, 1),
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+impl RecordsState {
+    /// Opens the records menu with `item` highlighted
+    fn new(menu: Box<StartMenuState>, item: RecordsItem) -> Self {
+        RecordsState {
+            menu,
+            items: ItemList::new(RecordsItem::into_enum_iter(), item as usize),
+        }
+    }
+}
+
+impl RecordsItem {
+    fn name(&self) -> &'static str {
+        match self {
+            RecordsItem::HighScores => "High Scores",
+            RecordsItem::Statistics => "Statistics",
+            RecordsItem::Achievements => "Achievements",
+            RecordsItem::Back => "Back",
+        }
+    }
+}
+
+impl GameMode {
+    fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+            GameMode::Tron => "Tron",
+        }
+    }
+}
+
+impl BiteRule {
+    fn name(&self) -> &'static str {
+        match self {
+            BiteRule::Crash => "Crash",
+            BiteRule::CutOff => "Cut Off",
+            BiteRule::Debris => "Debris",
+        }
+    }
+}
+
+impl GamePlayers {
+    fn count(&self) -> usize {
+        match self {
+            GamePlayers::One => 1,
+            GamePlayers::Two => 2,
+        }
+    }
 }
 
 impl SnakeState {
-    fn new() -> Self {
-        let body = RectilinearLine {
-            start: i32::ivec2(graphics::screen_middle().0 / 2, 0),
-            segments: VecDeque::from(vec![seg!(Direction::Down, 3)]),
-        };
+    /// Creates a snake heading down from the top of the play area at `x`
+    fn new(x: i32, color: u64, controller: Controller, difficulty: GameDifficulty) -> Self {
+        let body = new_snake_body(x, SNAKE_START_LENGTH);
         let direction = body.dir().unwrap();
         SnakeState {
-            movement_period: 6,
+            speed_curve: difficulty.speed_curve(),
             body,
-            color: 34,
+            color,
+            player_color: color,
             direction,
-            movement_frames: 0,
+            movement_progress: 0.0,
+            pending_growth: 0,
             turn_cooldown: 0,
+            straight_frames: 0,
+            longest_straight: 0,
+            effects: ActiveEffects::new(),
+            controller,
+            autopilot: false,
+            used_autopilot: false,
+            scoring: Scoring::new(difficulty.score_percent()),
+            tally: RoundTally::new(SNAKE_START_LENGTH),
+            alive: true,
+        }
+    }
+
+    fn die(&mut self, cause: DeathCause) {
+        self.alive = false;
+        self.tally.record_death(cause);
+    }
+
+    fn is_ai(&self) -> bool {
+        self.controller.is_ai()
+    }
+
+    /// Brings a dead snake back to life as a short snake at the top of the
+    /// play area, keeping its score but losing its combo and effects.
+    fn respawn(&mut self, x: i32) {
+        self.body = new_snake_body(x, SNAKE_RESPAWN_LENGTH);
+        self.direction = self.body.dir().unwrap();
+        self.movement_progress = 0.0;
+        self.pending_growth = 0;
+        self.turn_cooldown = 0;
+        self.straight_frames = 0;
+        self.effects.clear();
+        self.scoring.break_combo();
+        self.color = self.player_color;
+        self.alive = true;
+    }
+
+    /// The current speed level, which goes up as the snake grows
+    fn speed_level(&self) -> usize {
+        let growth = self.body.len().saturating_sub(SNAKE_START_LENGTH);
+        self.speed_curve.level(growth)
+    }
+
+    /// Number of frames between each movement step at the current speed
+    fn movement_period(&self) -> f32 {
+        let mut period = self.speed_curve.period(self.speed_level());
+        if self.effects.is_active(Effect::SpeedBoost) {
+            period *= 0.6;
+        }
+        if self.effects.is_active(Effect::SlowMotion) {
+            period *= 2.0;
+        }
+        period
+    }
+
+    /// The color to draw the snake with, which is tinted by the most recently
+    /// applied effect, flashing back to normal when it's about to run out.
+    fn draw_color(&self) -> u64 {
+        match self.effects.latest() {
+            Some(active) if !active.is_flashing() => active.effect.color(),
+            _ => self.color,
         }
     }
 }
 
 fn main() {
+    let arguments = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("{}\n{}", error, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+
     /* Initialize */
+    // random number generation
+    let mut ivec2_gen = IVec2Generator::new(arguments.seed.unwrap_or_else(rand::random));
+    // network matches
+    let (lockstep, game_state) = match arguments.command {
+        Command::Play => (
+            None,
+            GameState::StartMenu(StartMenuState::new(arguments.seed, has_saved_round())),
+        ),
+        Command::Benchmark(games) => {
+            run_autopilot_benchmark(games, &mut ivec2_gen);
+            return;
+        }
+        Command::Host(port) => {
+            println!("Waiting for another player to join on port {}...", port);
+            let setup = MatchSetup {
+                seed: arguments.seed.unwrap_or_else(rand::random),
+                difficulty: arguments.difficulty.unwrap_or(GameDifficulty::Normal as u8),
+            };
+            let lockstep = net::host(port, setup).unwrap_or_else(|error| exit_with_error(error));
+            (Some(lockstep), new_network_match(setup, 0))
+        }
+        Command::Join(address) => {
+            println!("Joining {}...", address);
+            let (lockstep, setup) =
+                net::join(&address).unwrap_or_else(|error| exit_with_error(error));
+            (Some(lockstep), new_network_match(setup, 1))
+        }
+        Command::Serve(port) => {
+            if let Err(error) = serve(port) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Watch(address) => {
+            if let Err(error) = spectate::watch(&address) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Replay(file) => {
+            let path = file.map(PathBuf::from).or_else(last_replay_path);
+            match load_playback(path) {
+                Ok(playback) => (None, GameState::Playback(Box::new(playback))),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+    // spectators
+    let broadcast = arguments.broadcast.map(|port| match Broadcast::bind(port) {
+        Ok(broadcast) => broadcast,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    });
+
     let window = pancurses::initscr();
     pancurses::curs_set(0);
     pancurses::noecho();
@@ -197,27 +1104,24 @@ fn main() {
     for color in 16..256 {
         pancurses::init_pair(color, color, pancurses::COLOR_BLACK);
     }
-    // random number generation
-    let ivec2_gen = IVec2Generator {
-        rng: rand::thread_rng(),
-        x_dist: Uniform::from(1..graphics::BORDER_WIDTH - 3),
-        y_dist: Uniform::from(1..graphics::BORDER_HEIGHT - 3),
-    };
 
     /* Setup initial state */
     let mut prev_time = platform::timing::get_microsec_timestamp();
     let mut program_state = ProgramState {
         elapsed_frames: 0,
         quit_requested: false,
-        keyboard_handler: KeyboardHandler::new(),
+        keyboard_handler: Box::new(KeyboardHandler::new()),
         ivec2_gen,
-        game_state: GameState::StartMenu(StartMenuState {
-            focused_area: StartMenuArea::Main,
-            menu_items: ItemList::new(StartMenuItem::into_enum_iter(), 0),
-            difficulty_items: ItemList::new(GameDifficulty::into_enum_iter(), 1),
-            difficulty: GameDifficulty::Normal,
-        }),
+        lockstep,
+        local: true,
+        unlocked: load_achievements(),
+        toasts: VecDeque::new(),
+        game_state,
     };
+    let broadcast_canvas = broadcast.map(|broadcast| {
+        let size = Rc::new(Cell::new((window.lines(), window.columns())));
+        AnsiCanvas::new(broadcast, size)
+    });
 
     /* Run program */
     loop {
@@ -237,96 +1141,647 @@ fn main() {
 
             program_state = update(program_state);
             draw(&program_state, &window);
+            if let Some(canvas) = &broadcast_canvas {
+                draw_broadcast(&program_state, &window, canvas);
+            }
         }
     }
 
+    if let Some(canvas) = &broadcast_canvas {
+        canvas.finish();
+    }
     pancurses::endwin();
 }
 
-fn update(mut program_state: ProgramState) -> ProgramState {
-    /* Update inputs */
-    let keyboard_handler = &mut program_state.keyboard_handler;
-    keyboard_handler.update();
-    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_ESCAPE) {
-        return ProgramState {
-            quit_requested: true,
-            ..program_state
+/// Size of the terminal of a telnet player, until it tells us otherwise
+const TELNET_DEFAULT_SIZE: (i32, i32) = (24, 80);
+
+/// How long to wait for keys from a telnet player each frame
+const TELNET_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Lets anyone connecting with telnet on `port` play, each connection getting
+/// a game of its own running on a thread of its own
+fn serve(port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Serving on port {}, connect with: telnet localhost {}", port, port);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Failed to accept connection: {}", error);
+                continue;
+            }
         };
+        thread::spawn(move || {
+            if let Err(error) = run_telnet_session(stream) {
+                eprintln!("Telnet session failed: {}", error);
+            }
+        });
     }
+    Ok(())
+}
 
-    /* Run current state */
-    let ivec2_gen = &mut program_state.ivec2_gen;
-    match program_state.game_state {
-        GameState::StartMenu(menu_state) => {
-            if menu_state.focused_area == StartMenuArea::Main {
-                let (menu_state, selected_item) = run_start_menu(menu_state, &keyboard_handler);
-                let (game_state, quit) = transition_start_menu(menu_state, selected_item);
-                program_state.game_state = if quit == QuitRequested::Yes {
-                    GameState::ProgramExit(0)
-                } else {
-                    game_state
-                }
-            } else {
-                let (menu_state, exit) = run_difficulty_menu(menu_state, &keyboard_handler);
-                program_state.game_state = GameState::StartMenu(StartMenuState {
-                    focused_area: if exit == ExitMenu::Yes {
-                        StartMenuArea::Main
-                    } else {
-                        StartMenuArea::Difficulty
-                    },
-                    ..menu_state
-                })
-            }
+/// Runs a game for the telnet player at the other end of `stream` until they
+/// quit or disconnect
+fn run_telnet_session(stream: TcpStream) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    println!("{} connected", peer);
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(TELNET_READ_TIMEOUT))?;
+    (&stream).write_all(&telnet::NEGOTIATION)?;
+
+    let window_size = Rc::new(Cell::new(TELNET_DEFAULT_SIZE));
+    let canvas = AnsiCanvas::new(stream.try_clone()?, window_size.clone());
+    let mut program_state = ProgramState {
+        elapsed_frames: 0,
+        quit_requested: false,
+        keyboard_handler: Box::new(TelnetKeyboard::new(stream, window_size)),
+        ivec2_gen: IVec2Generator::new(rand::random()),
+        lockstep: None,
+        local: false,
+        // what's unlocked over telnet isn't this computer's to keep
+        unlocked: Unlocked::default(),
+        toasts: VecDeque::new(),
+        // the saved round belongs to whoever plays at this computer
+        game_state: GameState::StartMenu(StartMenuState::new(None, false)),
+    };
+
+    // run update at 60 fps
+    let frame_period_60_fps = Duration::from_micros((1e6 / 60.0) as u64);
+    let mut next_frame = Instant::now();
+    while !program_state.quit_requested && !canvas.is_broken() {
+        program_state.elapsed_frames += 1;
+        program_state = update(program_state);
+        draw(&program_state, &canvas);
+
+        next_frame += frame_period_60_fps;
+        if let Some(wait) = next_frame.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+    }
+
+    canvas.finish();
+    println!("{} disconnected", peer);
+    Ok(())
+}
+
+/// Sends the frame that was just drawn on `window` to the spectators as well,
+/// sized the same as the window
+fn draw_broadcast(
+    program_state: &ProgramState,
+    window: &pancurses::Window,
+    canvas: &AnsiCanvas<Broadcast>,
+) {
+    canvas.resize(window.lines(), window.columns());
+    if canvas.writer().accept_spectators() {
+        canvas.redraw();
+    }
+    draw(program_state, canvas);
+}
+
+fn exit_with_error(error: NetError) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+/// How a round played with `settings` is set up, as kept in its replay
+fn replay_setup(settings: GameSettings, seed: u64) -> ReplaySetup {
+    ReplaySetup {
+        seed,
+        difficulty: settings.difficulty as u8,
+        mode: settings.mode as u8,
+        level: settings.level as u8,
+        bite_rule: settings.bite_rule as u8,
+        players: settings.players.count() as u8,
+        rivals: settings.rivals as u8,
+    }
+}
+
+/// The settings a replay was recorded with, or `None` if it was recorded with
+/// options that this version of the game doesn't have
+fn replayed_settings(setup: &ReplaySetup) -> Option<GameSettings> {
+    let level = setup.level as usize;
+    let rivals = setup.rivals as usize;
+    if level >= level::builtin_count() || rivals > MAX_RIVALS {
+        return None;
+    }
+    Some(GameSettings {
+        difficulty: GameDifficulty::into_enum_iter().nth(setup.difficulty as usize)?,
+        mode: GameMode::into_enum_iter().nth(setup.mode as usize)?,
+        level,
+        bite_rule: BiteRule::into_enum_iter().nth(setup.bite_rule as usize)?,
+        ghost: false,
+        players: GamePlayers::into_enum_iter()
+            .find(|players| players.count() == setup.players as usize)?,
+        rivals,
+        network_player: None,
+        seed: None,
+    })
+}
+
+/// Where the replay of the last round played is kept, if anywhere
+fn last_replay_path() -> Option<PathBuf> {
+    storage::data_dir().map(|dir| dir.join("replays").join(LAST_REPLAY_FILE))
+}
+
+/// Where the best replay of single player rounds with `settings` is kept, if
+/// anywhere. The modes score too differently to race each other, so each mode
+/// has a best replay of its own as well.
+fn best_replay_path(settings: &GameSettings) -> Option<PathBuf> {
+    let name = format!(
+        "best-{:?}-{:?}-{}.replay",
+        settings.difficulty,
+        settings.mode,
+        level::builtin(settings.level).name
+    );
+    storage::data_dir().map(|dir| dir.join("replays").join(name.to_lowercase()))
+}
+
+fn read_replay(path: &Path) -> io::Result<Replay> {
+    let file = fs::File::open(path)?;
+    Replay::read_from(&mut io::BufReader::new(file))
+}
+
+fn write_replay(path: &Path, replay: &Replay) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    replay.write_to(&mut bytes)?;
+    storage::write_atomically(path, &bytes)
+}
+
+/// Saves the replay of a round that has just ended over the one of the round
+/// before it, and keeps it as the best replay if it's a single player round
+/// that beat the best score
+fn save_replays(round: &RoundState) {
+    let replay = match &round.replay {
+        Some(replay) => Replay {
+            score: round.snakes[0].scoring.total() as u32,
+            ..replay.clone()
+        },
+        None => return,
+    };
+    if let Some(path) = last_replay_path() {
+        let _ = write_replay(&path, &replay);
+    }
+
+    let single_player = round.settings.is_single_player();
+    if let Some(path) = best_replay_path(&round.settings).filter(|_| single_player) {
+        let _lock = storage::lock_data_dir();
+        let best_score = read_replay(&path).map_or(0, |best| best.score);
+        if replay.score > best_score {
+            let _ = write_replay(&path, &replay);
+        }
+    }
+}
+
+/// Reads the replay at `path` and gets it ready to be played back from the
+/// start of the round
+fn load_playback(path: Option<PathBuf>) -> Result<PlaybackState, String> {
+    let path = path.ok_or("no replay file given, and no data folder to find the last round in")?;
+    let replay = read_replay(&path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let settings = replayed_settings(&replay.setup)
+        .ok_or_else(|| format!("{}: replay has options this game doesn't have", path.display()))?;
+    Ok(PlaybackState::new(replay, settings))
+}
+
+/// The best replay of single player rounds with `settings`, ready to be raced
+/// against as a ghost, if there is one
+fn load_ghost(settings: &GameSettings) -> Option<PlaybackState> {
+    let replay = read_replay(&best_replay_path(settings)?).ok()?;
+    let ghost_settings = replayed_settings(&replay.setup)?;
+    Some(PlaybackState::new(replay, ghost_settings))
+}
+
+/// The items of the start menu, which only has the continue item when there's
+/// a saved round to continue
+fn start_menu_items(can_continue: bool) -> ItemList<StartMenuItem> {
+    let items = StartMenuItem::into_enum_iter()
+        .filter(|&item| item != StartMenuItem::Continue || can_continue);
+    ItemList::new(items, 0)
+}
+
+fn saved_round_path() -> Option<PathBuf> {
+    storage::data_dir().map(|dir| dir.join(SAVED_ROUND_FILE))
+}
+
+fn has_saved_round() -> bool {
+    saved_round_path().is_some_and(|path| path.exists())
+}
+
+/// Whether `round` can be saved and continued later. Demo rounds have nothing
+/// worth saving, and network matches can't be continued alone.
+fn can_be_saved(round: &RoundState) -> bool {
+    round.replay.is_some() && round.settings.network_player.is_none()
+}
+
+/// Saves `round` over any round saved before it, so that it can be continued
+/// from the start menu later
+fn save_round(round: &RoundState) {
+    let replay = match &round.replay {
+        Some(replay) if can_be_saved(round) => replay.clone(),
+        _ => return,
+    };
+    let saved = SavedRound {
+        game_seed: round.seed,
+        state_hash: round_state_hash(round),
+        ghost: round.ghost.is_some(),
+        match_score: round.match_score.clone(),
+        replay,
+    };
+    storage::save_to_data_dir(SAVED_ROUND_FILE, |bytes| saved.write_to(bytes));
+}
+
+/// Saves the round being played when the game is left in the middle of it
+fn save_left_round(game_state: &GameState) {
+    if let GameState::OngoingRound(round) | GameState::Paused(round) = game_state {
+        save_round(round);
+    }
+}
+
+/// Removes the saved round once a round has been played to the end, since
+/// there's only room for one saved round
+fn remove_saved_round() {
+    if let Some(path) = saved_round_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Picks the saved round up where it was left, by playing its replay back up
+/// to there. The generator carries on from where the replay left it, so that
+/// the rest of the round goes on as if it had never been left. Returns `None`
+/// if there's no saved round, or if it can't be read.
+fn load_saved_round(generator: &mut IVec2Generator) -> Option<RoundState> {
+    let saved = SavedRound::read_from(&mut storage::open_in_data_dir(SAVED_ROUND_FILE)?).ok()?;
+    let settings = GameSettings {
+        ghost: saved.ghost,
+        ..replayed_settings(&saved.replay.setup)?
+    };
+    let frames = saved.replay.frames;
+    let playback = seek_replay(PlaybackState::new(saved.replay.clone(), settings), frames);
+    if round_state_hash(&playback.round) != saved.state_hash {
+        return None;
+    }
+
+    *generator = playback.generator;
+    generator.seed = saved.game_seed;
+    let mut round = playback.round;
+    round.seed = saved.game_seed;
+    round.match_score = saved.match_score;
+    round.replay = Some(saved.replay);
+    if settings.ghost {
+        round.ghost = load_ghost(&settings).map(|ghost| Box::new(seek_replay(ghost, frames)));
+    }
+    Some(round)
+}
+
+/// Which of the high score tables a round played with `settings` goes in
+fn high_score_key(settings: &GameSettings) -> high_scores::TableKey {
+    (settings.difficulty as u8, settings.mode as u8)
+}
+
+/// The high scores saved so far, which are empty until the first one is saved
+/// or if they can't be read
+fn load_high_scores() -> HighScores {
+    storage::open_in_data_dir(HIGH_SCORES_FILE)
+        .and_then(|mut file| HighScores::read_from(&mut file).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the high scores over the ones saved before
+fn save_high_scores(high_scores: &HighScores) {
+    storage::save_to_data_dir(HIGH_SCORES_FILE, |bytes| high_scores.write_to(bytes));
+}
+
+/// The lifetime statistics saved so far, which start out empty
+fn load_stats() -> Stats {
+    storage::open_in_data_dir(STATS_FILE)
+        .and_then(|mut file| Stats::read_from(&mut file).ok())
+        .unwrap_or_default()
+}
+
+/// Adds a round that has just ended to the lifetime statistics of the players
+/// on this computer. Demo rounds aren't played by anyone, and snakes that were
+/// steered by the autopilot at any point aren't played by the players alone,
+/// so they don't count.
+fn record_stats(round: &RoundState) {
+    let frames = match &round.replay {
+        Some(replay) => replay.frames,
+        None => return,
+    };
+    let players: Vec<(usize, RoundTally)> = round
+        .snakes
+        .iter()
+        .filter(|snake| {
+            matches!(snake.controller, Controller::Keyboard(_)) && !snake.used_autopilot
+        })
+        .map(|snake| (snake.scoring.total(), snake.tally))
+        .collect();
+    if players.is_empty() {
+        return;
+    }
+    let _lock = storage::lock_data_dir();
+    let mut stats = load_stats();
+    stats.record_round(frames, &players);
+    storage::save_to_data_dir(STATS_FILE, |bytes| stats.write_to(bytes));
+}
+
+/// The pages of the statistics screen, each with a title and the rows on it
+fn stats_pages(stats: &Stats) -> Vec<(&'static str, Vec<(&'static str, String)>)> {
+    let seconds = stats.frames_played / 60;
+    let time_played = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    vec![
+        (
+            "Games",
+            vec![
+                ("Games played", stats.games.to_string()),
+                ("Time played", time_played),
+                ("Total score", stats.total_score.to_string()),
+                ("Average score", stats.average_score().to_string()),
+            ],
+        ),
+        (
+            "Snakes",
+            vec![
+                ("Apples eaten", stats.apples.to_string()),
+                ("Longest snake", stats.longest_snake.to_string()),
+            ],
+        ),
+        (
+            "Deaths",
+            vec![
+                ("Into walls", stats.wall_deaths.to_string()),
+                ("Into themselves", stats.self_deaths.to_string()),
+                ("Into other snakes", stats.rival_deaths.to_string()),
+            ],
+        ),
+    ]
+}
+
+/// The achievements unlocked so far, which start out all locked
+fn load_achievements() -> Unlocked {
+    storage::open_in_data_dir(ACHIEVEMENTS_FILE)
+        .and_then(|mut file| Unlocked::read_from(&mut file).ok())
+        .unwrap_or_default()
+}
+
+/// What the players on this computer have done in `round` so far towards the
+/// achievements. Demo rounds and snakes that were on autopilot at any point
+/// don't earn anything.
+fn achievement_events(round: &RoundState) -> Vec<achievements::Event> {
+    if round.replay.is_none() {
+        return Vec::new();
+    }
+    let outlasted_rivals =
+        round.game_over && round.trails.is_some() && round.settings.rivals > 0;
+    let match_winner = round
+        .winner()
+        .filter(|_| round.game_over && round.match_score.is_some());
+    let mut events = Vec::new();
+    for (index, snake) in round.snakes.iter().enumerate().filter(|(_, snake)| {
+        matches!(snake.controller, Controller::Keyboard(_)) && !snake.used_autopilot
+    }) {
+        events.extend_from_slice(&[
+            achievements::Event::Length(snake.body.len()),
+            achievements::Event::Score(snake.scoring.total()),
+            achievements::Event::Apples(snake.tally.apples),
+            achievements::Event::Combo(snake.scoring.multiplier),
+        ]);
+        if round.board_cleared {
+            events.push(achievements::Event::ClearedBoard);
+        }
+        if outlasted_rivals && snake.alive {
+            events.push(achievements::Event::OutlastedRivals);
+        }
+        let won = round.board_cleared
+            || (outlasted_rivals && snake.alive)
+            || match_winner == Some(index);
+        if won {
+            events.push(achievements::Event::WonStraight(snake.longest_straight));
+        }
+    }
+    events
+}
+
+/// Unlocks the achievements that the players have earned in `round` so far,
+/// and returns the newly unlocked ones. When there are any, the ones of a
+/// `local` game are saved along with any that another game has saved since
+/// they were loaded, while the ones of a telnet session are only kept for as
+/// long as the session lasts.
+fn unlock_achievements(
+    unlocked: &mut Unlocked,
+    round: &RoundState,
+    local: bool,
+) -> Vec<&'static Achievement> {
+    let events = achievement_events(round);
+    let newly_unlocked = unlocked.unlock(&events, round.settings.difficulty as u8);
+    if local && !newly_unlocked.is_empty() {
+        let _lock = storage::lock_data_dir();
+        unlocked.merge(load_achievements());
+        storage::save_to_data_dir(ACHIEVEMENTS_FILE, |bytes| unlocked.write_to(bytes));
+    }
+    newly_unlocked
+}
+
+/// Starts a network match as `player`, set up the way the host wants it
+fn new_network_match(setup: MatchSetup, player: usize) -> GameState {
+    let settings = GameSettings {
+        difficulty: GameDifficulty::into_enum_iter()
+            .nth(setup.difficulty as usize)
+            .unwrap_or(GameDifficulty::Normal),
+        mode: GameMode::Classic,
+        level: 0,
+        bite_rule: BiteRule::Crash,
+        ghost: false,
+        players: GamePlayers::Two,
+        rivals: 0,
+        network_player: Some(player),
+        seed: Some(setup.seed), // both computers play the same game
+    };
+    GameState::RoundStart(RoundStartState {
+        frames: 0,
+        settings,
+        match_score: new_match_score(settings),
+        new_game: true,
+    })
+}
+
+fn update(mut program_state: ProgramState) -> ProgramState {
+    /* Update inputs */
+    program_state.keyboard_handler.update();
+    let keyboard_handler = program_state.keyboard_handler.as_ref();
+    let in_attract_mode = matches!(program_state.game_state, GameState::AttractMode(_));
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_ESCAPE) && !in_attract_mode {
+        if program_state.local {
+            save_left_round(&program_state.game_state);
+        }
+        return ProgramState {
+            quit_requested: true,
+            ..program_state
+        };
+    }
+
+    // show each toast for a while, one after the other
+    if let Some(toast) = program_state.toasts.front_mut() {
+        toast.frames += 1;
+        if toast.frames > TOAST_FRAMES {
+            program_state.toasts.pop_front();
+        }
+    }
+
+    /* Run current state */
+    let ivec2_gen = &mut program_state.ivec2_gen;
+    match program_state.game_state {
+        GameState::StartMenu(mut menu_state) => {
+            if keyboard_handler.any_key_pressed() {
+                menu_state.idle_frames = 0;
+            } else {
+                menu_state.idle_frames += 1;
+            }
+
+            if menu_state.idle_frames > ATTRACT_MODE_DELAY {
+                let round = RoundState::new_demo(ivec2_gen, menu_state.settings);
+                program_state.game_state = GameState::AttractMode(AttractModeState {
+                    menu: Box::new(menu_state),
+                    round,
+                    frames: 0,
+                });
+            } else if menu_state.focused_area == StartMenuArea::Main {
+                let (menu_state, selected_item) = run_start_menu(menu_state, keyboard_handler);
+                let (game_state, quit) =
+                    transition_start_menu(menu_state, selected_item, ivec2_gen);
+                program_state.game_state = if quit == QuitRequested::Yes {
+                    GameState::ProgramExit(0)
+                } else {
+                    game_state
+                }
+            } else {
+                let (menu_state, exit) = run_settings_menu(menu_state, keyboard_handler);
+                program_state.game_state = GameState::StartMenu(StartMenuState {
+                    focused_area: if exit == ExitMenu::Yes {
+                        StartMenuArea::Main
+                    } else {
+                        menu_state.focused_area
+                    },
+                    ..menu_state
+                })
+            }
+        }
+        GameState::AttractMode(attract_state) => {
+            program_state.game_state = if keyboard_handler.any_key_pressed() {
+                GameState::StartMenu(StartMenuState {
+                    idle_frames: 0,
+                    ..*attract_state.menu
+                })
+            } else {
+                GameState::AttractMode(run_attract_mode(attract_state, ivec2_gen))
+            };
         }
         GameState::RoundStart(start_state) => {
             let mut next_start_state = start_state;
             let wait_period = 90; // frames
             next_start_state.frames += 1;
             program_state.game_state = if next_start_state.frames > wait_period {
-                GameState::OngoingRound(RoundState::new(ivec2_gen, next_start_state.difficulty))
+                if next_start_state.new_game {
+                    ivec2_gen.start_game(next_start_state.settings.seed);
+                }
+                GameState::OngoingRound(RoundState::new(
+                    ivec2_gen,
+                    next_start_state.settings,
+                    next_start_state.match_score,
+                ))
             } else {
                 GameState::RoundStart(next_start_state)
             };
         }
-        GameState::OngoingRound(round) => {
-            let next_round = run_ongoing_round(round, &keyboard_handler, ivec2_gen);
-            program_state.game_state = if next_round.game_over {
-                GameState::RoundEnd(RoundEndState {
-                    round: next_round,
-                    frames: 0,
-                })
-            } else {
-                GameState::OngoingRound(next_round)
+        GameState::OngoingRound(round)
+            if keyboard_handler.key_pressed_now(PAUSE_KEY) && program_state.lockstep.is_none() =>
+        {
+            if program_state.local {
+                save_round(&round);
             }
+            program_state.game_state = GameState::Paused(round);
+        }
+        GameState::OngoingRound(mut round) => {
+            let autopilot_toggled = keyboard_handler.key_pressed_now(AUTOPILOT_KEY);
+            if autopilot_toggled {
+                toggle_autopilot(&mut round);
+            }
+            let mut actions = read_snake_actions(&round, keyboard_handler);
+            let synced = match &mut program_state.lockstep {
+                Some(lockstep) => exchange_actions(lockstep, &round, &mut actions),
+                None => Ok(()),
+            };
+            program_state.game_state = match synced {
+                Err(error) => GameState::NetworkError(error.to_string()),
+                Ok(()) => {
+                    if let Some(replay) = &mut round.replay {
+                        replay.record(&actions, autopilot_toggled);
+                    }
+                    let next_round = run_ongoing_round(round, &actions, ivec2_gen);
+                    let unlocked = unlock_achievements(
+                        &mut program_state.unlocked,
+                        &next_round,
+                        program_state.local,
+                    );
+                    program_state.toasts.extend(
+                        unlocked
+                            .into_iter()
+                            .map(|achievement| Toast {
+                                achievement,
+                                frames: 0,
+                            }),
+                    );
+                    if next_round.game_over {
+                        if program_state.local {
+                            save_replays(&next_round);
+                            record_stats(&next_round);
+                        }
+                        if program_state.local && can_be_saved(&next_round) {
+                            remove_saved_round();
+                        }
+                        GameState::RoundEnd(RoundEndState {
+                            round: next_round,
+                            frames: 0,
+                        })
+                    } else {
+                        GameState::OngoingRound(next_round)
+                    }
+                }
+            }
+        }
+        GameState::Paused(round) => {
+            program_state.game_state = if keyboard_handler.key_pressed_now(PAUSE_KEY) {
+                GameState::OngoingRound(round)
+            } else {
+                GameState::Paused(round)
+            };
         }
         GameState::RoundEnd(round_end_state) => {
             let next_round = run_round_ending(round_end_state);
             program_state.game_state = if next_round.frames < 80 {
                 GameState::RoundEnd(next_round)
             } else {
-                GameState::GameOver(GameOverState {
-                    final_score: next_round.round.score,
-                    selection: GameOverSelection::Restart,
-                    difficulty: next_round.round.difficulty,
-                })
+                transition_round_end(next_round.round, program_state.local)
             }
         }
+        GameState::RoundWinner(winner_state) => {
+            program_state.game_state = run_round_winner(winner_state, keyboard_handler);
+        }
         GameState::GameOver(game_over_state) => {
-            let selection = if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
-                GameOverSelection::Exit
-            } else if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
-                GameOverSelection::Restart
-            } else {
-                game_over_state.selection
-            };
+            let selection = run_game_over_selection(game_over_state.selection, keyboard_handler);
 
             program_state.game_state =
                 if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
                     match selection {
                         GameOverSelection::Restart => {
                             let generator = &mut program_state.ivec2_gen;
-                            let difficulty = game_over_state.difficulty;
-                            GameState::OngoingRound(RoundState::new(generator, difficulty))
+                            let settings = game_over_state.settings;
+                            generator.start_game(settings.seed);
+                            GameState::OngoingRound(RoundState::new(generator, settings, None))
                         }
                         GameOverSelection::Exit => {
                             GameState::ProgramExit(0)
@@ -339,6 +1794,39 @@ fn update(mut program_state: ProgramState) -> ProgramState {
                     })
                 }
         }
+        GameState::NameEntry(entry_state) => {
+            program_state.game_state = run_name_entry(entry_state, keyboard_handler);
+        }
+        GameState::Records(records_state) => {
+            program_state.game_state = run_records(records_state, keyboard_handler);
+        }
+        GameState::HighScores(high_scores_state) => {
+            program_state.game_state = run_high_scores(high_scores_state, keyboard_handler);
+        }
+        GameState::Statistics(statistics_state) => {
+            program_state.game_state = run_statistics(statistics_state, keyboard_handler);
+        }
+        GameState::Achievements(achievements_state) => {
+            program_state.game_state =
+                if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+                    let menu = achievements_state.menu;
+                    GameState::Records(RecordsState::new(menu, RecordsItem::Achievements))
+                } else {
+                    GameState::Achievements(achievements_state)
+                };
+        }
+        GameState::Playback(playback_state) => {
+            program_state.game_state =
+                GameState::Playback(Box::new(run_playback(*playback_state, keyboard_handler)));
+        }
+        GameState::NetworkError(message) => {
+            program_state.game_state =
+                if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+                    GameState::ProgramExit(0)
+                } else {
+                    GameState::NetworkError(message)
+                };
+        }
         GameState::ProgramExit(frames) => {
             let wait_period = 30; // frames
             if frames + 1 > wait_period {
@@ -353,54 +1841,152 @@ fn update(mut program_state: ProgramState) -> ProgramState {
 }
 
 /// output the current program state to the window
-fn draw(program_state: &ProgramState, window: &pancurses::Window) {
+fn draw(program_state: &ProgramState, window: &impl Canvas) {
     window.clear();
-    pancurses::curs_set(0);
-    pancurses::resize_term(0, 0);
 
     match &program_state.game_state {
         GameState::StartMenu(menu_state) => {
             draw_start_menu(&menu_state, &window);
         }
+        GameState::AttractMode(attract_state) => {
+            draw_attract_mode(attract_state, &window);
+        }
         GameState::RoundStart(_) => {
             draw_round_start(&window);
         }
         GameState::OngoingRound(round_state) => {
             draw_ongoing_round(round_state, &window);
         }
+        GameState::Paused(round_state) => {
+            draw_paused_round(round_state, &window);
+        }
         GameState::RoundEnd(end_state) => {
             draw_ongoing_round(&end_state.round, &window);
         }
+        GameState::RoundWinner(winner_state) => {
+            draw_round_winner_screen(winner_state, &window);
+        }
+        GameState::NameEntry(entry_state) => {
+            draw_name_entry(entry_state, &window);
+        }
         GameState::GameOver(game_over_state) => {
             draw_game_over_screen(&game_over_state, &window);
         }
+        GameState::Records(records_state) => {
+            draw_records(records_state, window);
+        }
+        GameState::HighScores(high_scores_state) => {
+            draw_high_scores(high_scores_state, &window);
+        }
+        GameState::Statistics(statistics_state) => {
+            draw_statistics(statistics_state, &window);
+        }
+        GameState::Achievements(_) => {
+            draw_achievements(&program_state.unlocked, window);
+        }
+        GameState::NetworkError(message) => {
+            draw_network_error(message, &window);
+        }
+        GameState::Playback(playback_state) => {
+            draw_playback(playback_state, &window);
+        }
         GameState::ProgramExit(_) => {
             draw_program_exit(&window);
         }
     }
+    if let Some(toast) = program_state.toasts.front() {
+        draw_toast(toast, &window);
+    }
 
     window.refresh();
 }
 
 /// Get which direction key is pressed, if any
-fn get_direction(keyboard_handler: &KeyboardHandler) -> Option<Direction> {
-    // todo extract keycodes into a lookup table parameter?
-    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
+fn get_direction(keyboard_handler: &dyn Keyboard, bindings: &KeyBindings) -> Option<Direction> {
+    if keyboard_handler.key_pressed_now(bindings.right) {
         Some(Direction::Right)
-    } else if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+    } else if keyboard_handler.key_pressed_now(bindings.left) {
         Some(Direction::Left)
-    } else if keyboard_handler.key_pressed_now(virtual_keycodes::VK_UP) {
+    } else if keyboard_handler.key_pressed_now(bindings.up) {
         Some(Direction::Up)
-    } else if keyboard_handler.key_pressed_now(virtual_keycodes::VK_DOWN) {
+    } else if keyboard_handler.key_pressed_now(bindings.down) {
         Some(Direction::Down)
     } else {
         None
     }
 }
 
+/// Get the direction each snake in the round is being steered in, if any
+fn read_snake_actions(
+    round: &RoundState,
+    keyboard_handler: &dyn Keyboard,
+) -> Vec<Option<Direction>> {
+    round
+        .snakes
+        .iter()
+        .map(|snake| match &snake.controller {
+            Controller::Keyboard(_) if snake.autopilot => None,
+            Controller::Keyboard(bindings) => get_direction(keyboard_handler, bindings),
+            Controller::Ai(_) | Controller::Network => None,
+        })
+        .collect()
+}
+
+/// Sends the action of the local player in a network match to the other
+/// computer, and fills in the action of the remote player in `actions`
+fn exchange_actions(
+    lockstep: &mut Lockstep,
+    round: &RoundState,
+    actions: &mut [Option<Direction>],
+) -> Result<(), NetError> {
+    let local_player = round.settings.network_player.unwrap_or(0);
+    let remote_action = lockstep.exchange(actions[local_player], round_state_hash(round))?;
+    for (snake, action) in round.snakes.iter().zip(actions.iter_mut()) {
+        if snake.controller == Controller::Network {
+            *action = remote_action;
+        }
+    }
+    Ok(())
+}
+
+/// Hashes everything about a round that affects how it plays out, so that the
+/// computers in a network match can tell if their rounds have drifted apart
+fn round_state_hash(round: &RoundState) -> u64 {
+    let mut hasher = binary::StableHasher::new();
+    for snake in &round.snakes {
+        for run in &snake.body.runs {
+            hasher.write_i32(run.start.x);
+            hasher.write_i32(run.start.y);
+            for segment in &run.segments {
+                hasher.write_u8(segment.dir as u8);
+                hasher.write_usize(segment.len);
+            }
+        }
+        hasher.write_u8(snake.direction as u8);
+        hasher.write_u32(snake.movement_progress.to_bits());
+        hasher.write_usize(snake.pending_growth);
+        for active in snake.effects.iter() {
+            hasher.write_u8(active.effect as u8);
+            hasher.write_usize(active.frames_left);
+        }
+        hasher.write_usize(snake.scoring.total());
+        hasher.write_u8(snake.alive as u8);
+    }
+    for obstacle in &round.obstacles {
+        hasher.write_usize(obstacle.pose);
+    }
+    hasher.write_u8(round.item.item as u8);
+    hasher.write_i32(round.item.pos.x);
+    hasher.write_i32(round.item.pos.y);
+    if let Some(frames_left) = round.item.frames_left {
+        hasher.write_usize(frames_left);
+    }
+    hasher.finish()
+}
+
 fn run_start_menu(
     mut menu_state: StartMenuState,
-    keyboard_handler: &KeyboardHandler,
+    keyboard_handler: &dyn Keyboard,
 ) -> (StartMenuState, Option<StartMenuItem>) {
     if keyboard_handler.key_pressed_now(virtual_keycodes::VK_UP) {
         menu_state.menu_items.move_back();
@@ -419,20 +2005,43 @@ fn run_start_menu(
     (menu_state, selected_item)
 }
 
-fn run_difficulty_menu(
+/// Run the option list of the currently focused start menu setting
+fn run_settings_menu(
     mut menu_state: StartMenuState,
-    keyboard_handler: &KeyboardHandler,
+    keyboard_handler: &dyn Keyboard,
 ) -> (StartMenuState, ExitMenu) {
-    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
-        menu_state.difficulty_items.move_back();
-    }
-
-    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
-        menu_state.difficulty_items.move_forward();
+    match menu_state.focused_area {
+        StartMenuArea::Players => {
+            run_option_list(&mut menu_state.player_items, keyboard_handler);
+            menu_state.settings.players = menu_state.player_items.current_item();
+        }
+        StartMenuArea::Rivals => {
+            run_option_list(&mut menu_state.rival_items, keyboard_handler);
+            menu_state.settings.rivals = menu_state.rival_items.current_item();
+        }
+        StartMenuArea::Difficulty => {
+            run_option_list(&mut menu_state.difficulty_items, keyboard_handler);
+            menu_state.settings.difficulty = menu_state.difficulty_items.current_item();
+        }
+        StartMenuArea::Mode => {
+            run_option_list(&mut menu_state.mode_items, keyboard_handler);
+            menu_state.settings.mode = menu_state.mode_items.current_item();
+        }
+        StartMenuArea::Level => {
+            run_option_list(&mut menu_state.level_items, keyboard_handler);
+            menu_state.settings.level = menu_state.level_items.current_item();
+        }
+        StartMenuArea::Bites => {
+            run_option_list(&mut menu_state.bite_items, keyboard_handler);
+            menu_state.settings.bite_rule = menu_state.bite_items.current_item();
+        }
+        StartMenuArea::Ghost => {
+            run_option_list(&mut menu_state.ghost_items, keyboard_handler);
+            menu_state.settings.ghost = menu_state.ghost_items.current_item();
+        }
+        StartMenuArea::Main => {}
     }
 
-    menu_state.difficulty = menu_state.difficulty_items.current_item();
-
     let menu_return = if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
         ExitMenu::Yes
     } else {
@@ -442,29 +2051,103 @@ fn run_difficulty_menu(
     (menu_state, menu_return)
 }
 
+/// Step through the options of a horizontal option list
+fn run_option_list<T: Copy>(items: &mut ItemList<T>, keyboard_handler: &dyn Keyboard) {
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+        items.move_back();
+    }
+
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
+        items.move_forward();
+    }
+}
+
 fn transition_start_menu(
     next_state: StartMenuState,
     selected_item: Option<StartMenuItem>,
+    ivec2_gen: &mut IVec2Generator,
 ) -> (GameState, QuitRequested) {
     match selected_item {
         Some(selected_item) => match selected_item {
-            StartMenuItem::Start => (
-                GameState::RoundStart(RoundStartState {
-                    frames: 0,
-                    difficulty: next_state.difficulty,
+            StartMenuItem::Continue => match load_saved_round(ivec2_gen) {
+                Some(round) => (GameState::Paused(round), QuitRequested::No),
+                // the save can't be continued, so it's no longer offered
+                None => (
+                    GameState::StartMenu(StartMenuState {
+                        menu_items: start_menu_items(false),
+                        ..next_state
+                    }),
+                    QuitRequested::No,
+                ),
+            },
+            StartMenuItem::Start => {
+                let settings = next_state.settings.for_mode();
+                (
+                    GameState::RoundStart(RoundStartState {
+                        frames: 0,
+                        settings,
+                        match_score: new_match_score(settings),
+                        new_game: true,
+                    }),
+                    QuitRequested::No,
+                )
+            }
+            StartMenuItem::Players => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Players,
+                    ..next_state
+                }),
+                QuitRequested::No,
+            ),
+            StartMenuItem::Rivals => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Rivals,
+                    ..next_state
                 }),
                 QuitRequested::No,
             ),
             StartMenuItem::Difficulty => (
                 GameState::StartMenu(StartMenuState {
-                    focused_area: match next_state.focused_area {
-                        StartMenuArea::Main => StartMenuArea::Difficulty,
-                        StartMenuArea::Difficulty => StartMenuArea::Main,
-                    },
+                    focused_area: StartMenuArea::Difficulty,
+                    ..next_state
+                }),
+                QuitRequested::No,
+            ),
+            StartMenuItem::Mode => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Mode,
+                    ..next_state
+                }),
+                QuitRequested::No,
+            ),
+            StartMenuItem::Level => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Level,
+                    ..next_state
+                }),
+                QuitRequested::No,
+            ),
+            StartMenuItem::Bites => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Bites,
                     ..next_state
                 }),
                 QuitRequested::No,
             ),
+            StartMenuItem::Ghost => (
+                GameState::StartMenu(StartMenuState {
+                    focused_area: StartMenuArea::Ghost,
+                    ..next_state
+                }),
+                QuitRequested::No,
+            ),
+            StartMenuItem::Records => (
+                GameState::Records(RecordsState::new(
+                    Box::new(next_state),
+                    RecordsItem::HighScores,
+                )),
+                QuitRequested::No,
+            ),
             StartMenuItem::Exit => (GameState::StartMenu(next_state), QuitRequested::Yes),
         },
         None => (GameState::StartMenu(next_state), QuitRequested::No),
@@ -475,58 +2158,974 @@ fn transition_start_menu(
 
 fn run_ongoing_round(
     round: RoundState,
-    keyboard_handler: &KeyboardHandler,
+    actions: &[Option<Direction>],
     ivec2_gen: &mut IVec2Generator,
 ) -> RoundState {
     let mut next_round = RoundState { ..round };
-    let mut snake = &mut next_round.snake;
+    let leaves_trail = next_round.trails.is_some();
+
+    // move the obstacles along their paths, crashing into any snake in the way
+    for obstacle in next_round.obstacles.iter_mut() {
+        obstacle.tick();
+    }
+    next_round.obstacles.retain(|obstacle| !obstacle.has_crumbled());
+    let obstacles = &next_round.obstacles;
+    for snake in next_round.snakes.iter_mut().filter(|snake| snake.alive) {
+        if is_hit_by_obstacle(snake, obstacles, leaves_trail) {
+            snake.die(DeathCause::Wall);
+        }
+    }
+
+    // let the computer controlled snakes decide where to go
+    let ai_actions: Vec<Option<Direction>> = (0..next_round.snakes.len())
+        .map(|index| run_snake_ai(&next_round, index, ivec2_gen))
+        .collect();
+
+    // steer and move each snake
+    let heads_before: Vec<IVec2> = next_round
+        .snakes
+        .iter()
+        .map(|snake| snake.body.head())
+        .collect();
+    let all_actions = actions
+        .iter()
+        .zip(ai_actions)
+        .map(|(&action, ai_action)| action.or(ai_action));
+    let (wall, arena, obstacles) = (&next_round.wall, next_round.arena, &next_round.obstacles);
+    // portals can lead past a wall that a survival arena has closed in to
+    let hits_wall = |point: IVec2| {
+        wall.collides_with_point(point)
+            || arena.is_some_and(|arena| !arena.contains(point))
+            || obstacles.iter().any(|obstacle| obstacle.covers(point))
+    };
+    for (snake, action) in next_round.snakes.iter_mut().zip(all_actions) {
+        if snake.alive {
+            move_snake(snake, action, &next_round.level, hits_wall, leaves_trail);
+        }
+    }
+
+    // check if any snake collided with itself or another snake
+    if next_round.trails.is_some() {
+        run_tron_crashes(&mut next_round, &heads_before);
+    } else {
+        if next_round.settings.bite_rule != BiteRule::Crash {
+            run_self_bites(&mut next_round);
+        }
+        let crashes: Vec<Option<DeathCause>> = (0..next_round.snakes.len())
+            .map(|index| find_crash(&next_round.snakes, index))
+            .collect();
+        for (snake, crash) in next_round.snakes.iter_mut().zip(crashes) {
+            if let Some(cause) = crash {
+                snake.die(cause);
+            }
+        }
+    }
+
+    // in time attack, dying only costs time
+    if let Some(mut clock) = next_round.clock {
+        clock.tick();
+        for index in 0..next_round.snakes.len() {
+            let snake = &next_round.snakes[index];
+            if !snake.alive && !snake.is_ai() {
+                clock.record_death();
+                let x = find_respawn_x(&next_round, index);
+                next_round.snakes[index].respawn(x);
+            }
+        }
+        next_round.clock = Some(clock);
+    }
+
+    // in survival, the walls close in every now and then
+    let arena_closed = next_round.arena.as_mut().is_some_and(|arena| arena.tick());
+    if arena_closed {
+        close_arena(&mut next_round, ivec2_gen);
+    }
+
+    if next_round.has_items() {
+        run_round_items(&mut next_round, ivec2_gen);
+    }
+
+    // the ghost keeps pace with the round until its own round is over
+    next_round.ghost = next_round.ghost.map(|ghost| {
+        if ghost.frame < ghost.replay.frames {
+            Box::new(play_replay_frame(*ghost))
+        } else {
+            ghost
+        }
+    });
+
+    next_round.game_over = next_round.is_over();
+    next_round
+}
+
+/// Finds a column along the top of the play area where the snake at `index`
+/// can respawn with room to spare, clear of the other snakes and anything
+/// that's in the way. Columns closer to the middle are tried first, and the
+/// middle is used if there's no room anywhere.
+fn find_respawn_x(round: &RoundState, index: usize) -> i32 {
+    let middle = (graphics::BORDER_WIDTH - 2) / 2;
+    let is_free = |point| {
+        is_open_for_item(point, round.arena.as_ref(), &round.level, &round.obstacles)
+            && !round.snakes.iter().enumerate().any(|(other_index, other)| {
+                other_index != index && other.alive && other.body.collides_with_point(point)
+            })
+    };
+    let room = (SNAKE_RESPAWN_LENGTH + RESPAWN_CLEARANCE) as i32;
+    (0..middle)
+        .flat_map(|offset| [middle - offset, middle + offset])
+        .find(|&x| (0..room).all(|y| is_free(i32::ivec2(x, y))))
+        .unwrap_or(middle)
+}
+
+/// Whether an obstacle has moved into the snake. In tron that only counts for
+/// the head of the snake, since the rest of it is a trail left behind.
+fn is_hit_by_obstacle(snake: &SnakeState, obstacles: &[Obstacle], leaves_trail: bool) -> bool {
+    let points = if leaves_trail {
+        vec![snake.body.head()]
+    } else {
+        snake.body.points()
+    };
+    points
+        .iter()
+        .any(|&point| obstacles.iter().any(|obstacle| obstacle.covers(point)))
+}
+
+/// Lets the snakes eat the item on the board, and moves the item around
+fn run_round_items(next_round: &mut RoundState, ivec2_gen: &mut IVec2Generator) {
+    // check if collision with item
+    let item_pos = next_round.item.pos;
+    let eater = next_round
+        .snakes
+        .iter_mut()
+        .find(|snake| snake.alive && snake.body.head() == item_pos);
+    if let Some(snake) = eater {
+        eat_item(snake, &next_round.item);
+        if let Some(clock) = &mut next_round.clock {
+            if next_round.item.item.is_apple() && !snake.is_ai() {
+                clock.apples += 1;
+            }
+        }
+
+        // make new item
+        replace_item(next_round, ivec2_gen);
+    }
+
+    // pull nearby apples towards the head of snakes with magnets
+    for snake in next_round.snakes.iter().filter(|snake| snake.alive) {
+        if let Some(frames_left) = snake.effects.frames_left(Effect::Magnet) {
+            if frames_left % MAGNET_PULL_PERIOD == 0 {
+                let head = snake.body.head();
+                next_round.item = pull_item_towards(
+                    next_round.item,
+                    head,
+                    &next_round.snakes,
+                    &next_round.level,
+                );
+            }
+        }
+    }
+
+    // replace items that have been lying around for too long
+    next_round.item.tick();
+    if next_round.item.has_expired() {
+        replace_item(next_round, ivec2_gen);
+    }
+}
+
+/// Cuts off the part of every snake that has bitten itself from where it bit
+/// and back, taking off points for every cell lost. Depending on the bite
+/// rule the part either falls off or is left behind as debris for a while.
+fn run_self_bites(round: &mut RoundState) {
+    for snake in round.snakes.iter_mut() {
+        let bit_itself = snake.alive
+            && !snake.effects.is_active(Effect::Ghost)
+            && snake.body.is_self_overlapping();
+        if !bit_itself {
+            continue;
+        }
+        let head = snake.body.head();
+        let cut_off = match snake.body.cut_at(head) {
+            Some(cut_off) => cut_off,
+            None => continue,
+        };
+        let mut cells: Vec<IVec2> = cut_off.iter().flat_map(|run| run.points()).collect();
+        snake
+            .scoring
+            .deduct(cells.len() * BITE_PENALTY_PER_CELL, head);
+        if round.settings.bite_rule == BiteRule::Debris {
+            // the bitten cell is where the head is now, so it's left out of
+            // the debris for the snake not to crash into it right away
+            cells.retain(|&cell| cell != head);
+            round.obstacles.push(Obstacle::debris(cells, DEBRIS_FRAMES));
+        }
+    }
+}
+
+/// Checks if any light cycle in a tron round has run into a trail, given
+/// where the heads of the cycles were before moving. Crashed cycles lose
+/// their trails, and every player still riding scores for each crashed rival.
+fn run_tron_crashes(round: &mut RoundState, heads_before: &[IVec2]) {
+    let trails = match &mut round.trails {
+        Some(trails) => trails,
+        None => return,
+    };
+    let new_heads: Vec<Option<IVec2>> = round
+        .snakes
+        .iter()
+        .zip(heads_before)
+        .map(|(snake, &head_before)| {
+            let head = snake.body.head();
+            if snake.alive && head != head_before {
+                Some(head)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let crashed = tron::find_crashes(trails, &new_heads);
+    let causes: Vec<DeathCause> = new_heads
+        .iter()
+        .enumerate()
+        .map(|(index, head)| {
+            if head.and_then(|head| trails.owner(head)) == Some(index) {
+                DeathCause::Itself
+            } else {
+                DeathCause::Rival
+            }
+        })
+        .collect();
+    let mut crash_sites = Vec::new();
+    for (index, snake) in round.snakes.iter_mut().enumerate() {
+        if crashed[index] {
+            snake.die(causes[index]);
+        }
+        match new_heads[index] {
+            Some(head) if snake.alive => trails.cover(head, index),
+            // cycles also crash into the wall without moving
+            _ if !snake.alive && trails.owner(heads_before[index]) == Some(index) => {
+                trails.clear_owner(index);
+                if snake.is_ai() {
+                    crash_sites.push(heads_before[index]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for crash_site in crash_sites {
+        for snake in round.snakes.iter_mut() {
+            if snake.alive && !snake.is_ai() {
+                let len = snake.body.len();
+                snake.scoring.award(TRON_CRASH_POINTS, len, crash_site);
+            }
+        }
+    }
+}
+
+/// Moves the wall of a survival round in to where the arena has closed in to,
+/// killing any snake caught by the wall and moving the item out of its way.
+fn close_arena(round: &mut RoundState, ivec2_gen: &mut IVec2Generator) {
+    let arena = match round.arena {
+        Some(arena) => arena,
+        None => return,
+    };
+    round.wall = new_play_area_wall(arena.inset);
+    for snake in round.snakes.iter_mut().filter(|snake| snake.alive) {
+        if snake.body.points().iter().any(|&point| !arena.contains(point)) {
+            snake.die(DeathCause::Wall);
+        }
+    }
+    if !arena.contains(round.item.pos) {
+        replace_item(round, ivec2_gen);
+    }
+}
+
+/// Lets a computer controlled snake, or a snake on autopilot, pick which way to
+/// go when it's about to move. Returns `None` for snakes steered by a player,
+/// or if the snake wants to keep going in the direction it's already going.
+fn run_snake_ai(
+    round: &RoundState,
+    index: usize,
+    ivec2_gen: &mut IVec2Generator,
+) -> Option<Direction> {
+    let snake = &round.snakes[index];
+    if !snake.alive || snake.movement_progress + 1.0 < snake.movement_period() {
+        return None;
+    }
+    let direction = match snake.controller {
+        Controller::Keyboard(_) if snake.autopilot => run_autopilot(round, index)?,
+        Controller::Keyboard(_) | Controller::Network => return None,
+        Controller::Ai(personality) => run_ai_personality(round, index, personality, ivec2_gen),
+    };
+    if direction != snake.direction {
+        Some(direction)
+    } else {
+        None
+    }
+}
+
+/// Lets the autopilot steer a snake along a Hamiltonian cycle over the play
+/// area, taking shortcuts towards the item when it's safe to do so.
+fn run_autopilot(round: &RoundState, index: usize) -> Option<Direction> {
+    let snake = &round.snakes[index];
+    let cycle = HamiltonianCycle::new(graphics::BORDER_WIDTH - 2, graphics::BORDER_HEIGHT - 2)?;
+    let grid = new_path_grid(round);
+    let target = if !round.has_items() || round.item.item == Item::PoisonApple {
+        None
+    } else {
+        Some(round.item.pos)
+    };
+    autopilot::choose_direction(
+        &cycle,
+        &grid,
+        snake.body.head(),
+        snake.body.tail(),
+        snake.body.len(),
+        target,
+    )
+}
+
+/// Lets a computer controlled snake pick which way to go based on its
+/// personality
+fn run_ai_personality(
+    round: &RoundState,
+    index: usize,
+    personality: AiPersonality,
+    ivec2_gen: &mut IVec2Generator,
+) -> Direction {
+    let snake = &round.snakes[index];
+
+    let mut grid = new_path_grid(round);
+    let target = if !round.has_items() {
+        None
+    } else if round.item.item == Item::PoisonApple {
+        // only the easy snakes are silly enough to go for poison
+        if personality != AiPersonality::Easy {
+            grid.block(round.item.pos);
+        }
+        None
+    } else {
+        Some(round.item.pos)
+    };
+    let other_heads: Vec<IVec2> = round
+        .snakes
+        .iter()
+        .enumerate()
+        .filter(|&(other_index, other)| other_index != index && other.alive)
+        .map(|(_, other)| other.body.head())
+        .collect();
+
+    ai::choose_direction(
+        personality,
+        &grid,
+        ai::AiSnake {
+            head: snake.body.head(),
+            direction: snake.direction,
+            len: snake.body.len(),
+        },
+        target,
+        &other_heads,
+        &mut ivec2_gen.rng,
+    )
+}
+
+/// Turns the autopilot on or off for the player, as long as there is only one
+/// player in the round. The autopilot follows a cycle through every cell of
+/// the play area, which portals and obstacles would cut short, so it only
+/// plays levels without any.
+fn toggle_autopilot(round: &mut RoundState) {
+    if !round.level.portals.is_empty() || !round.level.obstacles.is_empty() {
+        return;
+    }
+    let mut players = round.snakes.iter_mut().filter(|snake| !snake.is_ai());
+    if let (Some(player), None) = (players.next(), players.next()) {
+        player.autopilot = !player.autopilot;
+        player.used_autopilot |= player.autopilot;
+    }
+}
+
+/// Plays `games` single player rounds on autopilot as fast as possible without
+/// drawing anything, and prints how well the autopilot did.
+fn run_autopilot_benchmark(games: usize, ivec2_gen: &mut IVec2Generator) {
+    let settings = GameSettings {
+        difficulty: GameDifficulty::Normal,
+        mode: GameMode::Classic,
+        level: 0,
+        bite_rule: BiteRule::Crash,
+        ghost: false,
+        players: GamePlayers::One,
+        rivals: 0,
+        network_player: None,
+        seed: None,
+    };
+    println!("seed: {}", ivec2_gen.seed);
+    let start_time = Instant::now();
+    let mut boards_cleared = 0;
+    let mut total_length = 0;
+    let mut total_score = 0;
+
+    for game in 0..games {
+        let mut round = RoundState::new(ivec2_gen, settings, None);
+        round.snakes[0].autopilot = true;
+        let mut frames = 0;
+        while !round.game_over && frames < BENCHMARK_MAX_FRAMES {
+            round = run_ongoing_round(round, &[None], ivec2_gen);
+            frames += 1;
+        }
+
+        let snake = &round.snakes[0];
+        let outcome = if round.board_cleared {
+            "cleared"
+        } else if snake.alive {
+            "timed out"
+        } else {
+            "crashed"
+        };
+        println!(
+            "game {}: {}, length {}, score {}, {} frames",
+            game + 1,
+            outcome,
+            snake.body.len(),
+            snake.scoring.total(),
+            frames
+        );
+        boards_cleared += round.board_cleared as usize;
+        total_length += snake.body.len();
+        total_score += snake.scoring.total();
+    }
+
+    if games > 0 {
+        println!(
+            "boards cleared: {}/{}, average length: {}, average score: {}, time: {:.1}s",
+            boards_cleared,
+            games,
+            total_length / games,
+            total_score / games,
+            start_time.elapsed().as_secs_f32()
+        );
+    }
+}
+
+/// Creates a path finding grid over the play area, with the wall, the portals
+/// and all living snakes blocked. Paths through portals aren't looked for, so
+/// the computer keeps its snakes out of them. The obstacles are blocked both
+/// where they are and where they're about to move.
+fn new_path_grid(round: &RoundState) -> ai::Grid {
+    let mut grid = ai::Grid::new(graphics::BORDER_WIDTH - 2, graphics::BORDER_HEIGHT - 2);
+    grid.block_line(&round.wall);
+    for portal in round.level.portals.iter().flatten() {
+        grid.block(portal.pos);
+    }
+    for obstacle in &round.obstacles {
+        for cell in obstacle.upcoming_cells(1) {
+            grid.block(cell);
+        }
+    }
+    for snake in round.snakes.iter().filter(|snake| snake.alive) {
+        for run in &snake.body.runs {
+            grid.block_line(run);
+        }
+    }
+    grid
+}
 
+/// Plays the demo round of the attract mode, starting a new one whenever the
+/// computer loses.
+fn run_attract_mode(state: AttractModeState, ivec2_gen: &mut IVec2Generator) -> AttractModeState {
+    let actions = vec![None; state.round.snakes.len()];
+    let next_round = run_ongoing_round(state.round, &actions, ivec2_gen);
+    let round = if next_round.game_over {
+        RoundState::new_demo(ivec2_gen, state.menu.settings)
+    } else {
+        next_round
+    };
+    AttractModeState {
+        round,
+        frames: state.frames + 1,
+        ..state
+    }
+}
+
+/// Plays back a replay at the chosen speed. Space pauses, S steps a single
+/// frame, the number keys pick the speed and the left and right arrow keys
+/// seek backwards and forwards.
+fn run_playback(state: PlaybackState, keyboard_handler: &dyn Keyboard) -> PlaybackState {
+    let mut state = state;
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_SPACE) {
+        state.paused = !state.paused;
+    }
+    for speed in 0..PLAYBACK_SPEEDS.len() {
+        if keyboard_handler.key_pressed_now(virtual_keycodes::VK_1 + speed as KeyCode) {
+            state.speed = speed;
+        }
+    }
+
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+        let target = state.frame.saturating_sub(PLAYBACK_SEEK_FRAMES);
+        return seek_replay(state, target);
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
+        let target = state.frame + PLAYBACK_SEEK_FRAMES;
+        return seek_replay(state, target);
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_S) {
+        state.paused = true;
+        let target = state.frame + 1;
+        return seek_replay(state, target);
+    }
+
+    if !state.paused {
+        state.progress += PLAYBACK_SPEEDS[state.speed];
+        while state.progress >= 1.0 && state.frame < state.replay.frames {
+            state.progress -= 1.0;
+            state = play_replay_frame(state);
+        }
+    }
+    if state.frame == state.replay.frames {
+        state.progress = 0.0;
+    }
+    state
+}
+
+/// Moves the playback to `target`, starting the round over when going
+/// backwards, since a round can only be played forwards
+fn seek_replay(state: PlaybackState, target: u32) -> PlaybackState {
+    let target = u32::min(target, state.replay.frames);
+    let mut state = if target < state.frame {
+        let settings = state.round.settings;
+        PlaybackState {
+            speed: state.speed,
+            paused: state.paused,
+            ..PlaybackState::new(state.replay, settings)
+        }
+    } else {
+        state
+    };
+    while state.frame < target {
+        state = play_replay_frame(state);
+    }
+    state
+}
+
+/// Plays the next frame of a replay, doing what the players did on it
+fn play_replay_frame(state: PlaybackState) -> PlaybackState {
+    let mut round = state.round;
+    let mut generator = state.generator;
+    let (actions, autopilot_toggled) = state.replay.actions_at(state.frame, round.snakes.len());
+    if autopilot_toggled {
+        toggle_autopilot(&mut round);
+    }
+    PlaybackState {
+        round: run_ongoing_round(round, &actions, &mut generator),
+        generator,
+        frame: state.frame + 1,
+        ..state
+    }
+}
+
+/// Steers the snake in the direction of `action` and moves it forward when
+/// it's time to, killing the snake if it's about to run into a wall. A snake
+/// that `leaves_trail` or still has growing to do grows by one cell each step
+/// instead of moving its tail along, and a snake moving into a portal of the
+/// `level` comes out of the other portal of the pair.
+fn move_snake(
+    snake: &mut SnakeState,
+    action: Option<Direction>,
+    level: &Level,
+    hits_wall: impl Fn(IVec2) -> bool,
+    leaves_trail: bool,
+) {
     // track frames
-    snake.movement_frames += 1;
+    let movement_period = snake.movement_period();
+    snake.movement_progress += 1.0;
     snake.turn_cooldown = snake.turn_cooldown.saturating_sub(1);
+    snake.straight_frames += 1;
+    snake.longest_straight = usize::max(snake.longest_straight, snake.straight_frames);
+    snake.effects.tick();
+    snake.scoring.tick();
+
+    // turn sideways
+    if let Some(new_direction) = action {
+        // make sure we're turning 90 degrees only, and not too often
+        if new_direction != snake.direction.opposite() && snake.turn_cooldown == 0 {
+            if new_direction != snake.direction {
+                snake.straight_frames = 0;
+            }
+            snake.direction = new_direction;
+            snake.movement_progress = movement_period;
+            // add a delay to when next turn can happen, to prevent from moving
+            // very fast when moving in a diagonal
+            snake.turn_cooldown = (movement_period / 2.0) as usize;
+        }
+    }
+
+    // move snake body, keeping any fraction of a frame left over so that
+    // fractional movement periods average out over time
+    if snake.movement_progress >= movement_period {
+        // check if about to hit a wall
+        let head_plus_one = snake.body.head() + snake.direction.unit();
+        if hits_wall(head_plus_one) {
+            snake.die(DeathCause::Wall);
+            return;
+        }
+
+        // the tail stays put while growing, so it never ends up anywhere the
+        // snake hasn't already been
+        let grows = leaves_trail || snake.pending_growth > 0;
+        snake.pending_growth = snake.pending_growth.saturating_sub(1);
+
+        match level.teleport(head_plus_one, snake.direction) {
+            Some((exit, exit_direction)) => {
+                // the other portal can send the snake straight into a wall
+                if hits_wall(exit) {
+                    snake.die(DeathCause::Wall);
+                    return;
+                }
+                if !grows {
+                    snake.body.shrink_tail();
+                }
+                snake.body.jump_head(exit);
+                snake.direction = exit_direction;
+            }
+            None if grows => snake.body.extend_head(snake.direction),
+            None => snake.body.move_forward(snake.direction),
+        }
+        snake.tally.longest = usize::max(snake.tally.longest, snake.body.len());
+        snake.movement_progress -= movement_period;
+    }
+}
+
+/// Checks if the snake at `index` has run into itself or into another snake,
+/// and which of them it was. Two snakes colliding head-on both count as having
+/// crashed.
+fn find_crash(snakes: &[SnakeState], index: usize) -> Option<DeathCause> {
+    let snake = &snakes[index];
+    if !snake.alive {
+        return None;
+    }
+
+    // check if collided with self, unless able to pass through self
+    if !snake.effects.is_active(Effect::Ghost) && snake.body.is_self_overlapping() {
+        return Some(DeathCause::Itself);
+    }
+
+    // check if collided with any other snake
+    let head = snake.body.head();
+    snakes
+        .iter()
+        .enumerate()
+        .filter(|&(other_index, other)| other_index != index && other.alive)
+        .any(|(_, other)| other.body.collides_with_point(head))
+        .then_some(DeathCause::Rival)
+}
+
+/// Grows or shrinks the snake, applies any effect and awards the points that
+/// come with the item. The snake doesn't grow right away, but over as many of
+/// its next moves as it has cells to grow by. Shrinking takes away from any
+/// growing still left to do before it takes cells off the tail.
+fn eat_item(snake: &mut SnakeState, board_item: &BoardItem) {
+    let item = board_item.item;
+    snake.pending_growth += item.growth();
+    for _ in 0..item.shrinkage() {
+        if snake.pending_growth > 0 {
+            snake.pending_growth -= 1;
+        } else if snake.body.len() > SNAKE_MIN_LENGTH {
+            snake.body.shrink_tail();
+        }
+    }
+    if let Some(effect) = item.effect() {
+        snake.effects.add(effect);
+    }
+    if item == Item::PoisonApple {
+        snake.scoring.break_combo();
+    }
+    if item.is_apple() {
+        snake.tally.apples += 1;
+    }
+    let snake_len = snake.body.len();
+    snake.scoring.award(item.points(), snake_len, board_item.pos);
+}
+
+/// Moves an apple lying within the magnet radius one step closer to `head`, as
+/// long as it doesn't end up on any of the `snakes` or on a portal.
+fn pull_item_towards(
+    item: BoardItem,
+    head: IVec2,
+    snakes: &[SnakeState],
+    level: &Level,
+) -> BoardItem {
+    let delta = head - item.pos;
+    let distance = delta.x.abs() + delta.y.abs();
+    if !item.item.is_apple() || distance > MAGNET_RADIUS || distance == 0 {
+        return item;
+    }
+
+    let step = if delta.x.abs() >= delta.y.abs() {
+        i32::ivec2(delta.x.signum(), 0)
+    } else {
+        i32::ivec2(0, delta.y.signum())
+    };
+    let pos = item.pos + step;
+    if level.is_portal(pos) {
+        return item;
+    }
+    if pos != head && snakes.iter().any(|snake| snake.body.collides_with_point(pos)) {
+        return item;
+    }
+
+    BoardItem { pos, ..item }
+}
+
+/// Moves on from a finished round, either to the game over screen or, when
+/// playing a versus match, to the screen showing who won the round. Only a
+/// `local` round, played at this computer, can go in its high scores.
+fn transition_round_end(round: RoundState, local: bool) -> GameState {
+    let winner = round.winner();
+    match round.match_score {
+        Some(mut match_score) => {
+            match_score.record_round(winner);
+            GameState::RoundWinner(RoundWinnerState {
+                winner,
+                match_score,
+                settings: round.settings,
+                selection: GameOverSelection::Restart,
+                frames: 0,
+            })
+        }
+        None => {
+            let scoring = &round.snakes[0].scoring;
+            let outlasted_rivals = round.trails.is_some() && round.snakes[0].alive;
+            let game_over = GameOverState {
+                board_cleared: round.board_cleared,
+                outlasted_rivals,
+                clock: round.clock,
+                final_score: scoring.total(),
+                breakdown: scoring.breakdown,
+                selection: GameOverSelection::Restart,
+                settings: round.settings,
+                seed: round.seed,
+                high_score: None,
+            };
+            // the autopilot's points aren't the player's to put in the high scores
+            let key = high_score_key(&round.settings);
+            let score = game_over.final_score as u32;
+            let counts = local && !round.snakes[0].used_autopilot;
+            if counts && load_high_scores().qualifies(key, score) {
+                GameState::NameEntry(NameEntryState {
+                    game_over,
+                    name: *b"AAA",
+                    cursor: 0,
+                })
+            } else {
+                GameState::GameOver(game_over)
+            }
+        }
+    }
+}
+
+/// Lets the player pick the letters of their name with the arrow keys, or by
+/// typing them, and puts their score in the high scores once they're done.
+fn run_name_entry(state: NameEntryState, keyboard_handler: &dyn Keyboard) -> GameState {
+    let mut state = state;
+    let letter = &mut state.name[state.cursor];
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_UP) {
+        *letter = if *letter == b'Z' { b'A' } else { *letter + 1 };
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_DOWN) {
+        *letter = if *letter == b'A' { b'Z' } else { *letter - 1 };
+    }
+    let typed = (b'A'..=b'Z').find(|&letter| {
+        keyboard_handler.key_pressed_now(virtual_keycodes::VK_A + (letter - b'A') as KeyCode)
+    });
+    if let Some(typed) = typed {
+        *letter = typed;
+    }
+
+    let last = high_scores::NAME_LENGTH - 1;
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+        state.cursor = state.cursor.saturating_sub(1);
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) || typed.is_some() {
+        state.cursor = usize::min(state.cursor + 1, last);
+    }
+
+    if !keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+        return GameState::NameEntry(state);
+    }
+    let _lock = storage::lock_data_dir();
+    let mut high_scores = load_high_scores();
+    let high_score = high_scores.insert(
+        high_score_key(&state.game_over.settings),
+        HighScore {
+            name: state.name,
+            score: state.game_over.final_score as u32,
+        },
+    );
+    save_high_scores(&high_scores);
+    GameState::GameOver(GameOverState {
+        high_score,
+        ..state.game_over
+    })
+}
+
+/// Moves between the records with the up and down arrow keys, and opens the
+/// one picked
+fn run_records(state: RecordsState, keyboard_handler: &dyn Keyboard) -> GameState {
+    let mut state = state;
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_UP) {
+        state.items.move_back();
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_DOWN) {
+        state.items.move_forward();
+    }
+    if !keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+        return GameState::Records(state);
+    }
+
+    let menu = state.menu;
+    match state.items.current_item() {
+        RecordsItem::HighScores => {
+            let settings = menu.settings;
+            GameState::HighScores(HighScoresState {
+                menu,
+                high_scores: load_high_scores(),
+                difficulty_items: ItemList::new(
+                    GameDifficulty::into_enum_iter(),
+                    settings.difficulty as usize,
+                ),
+                mode_items: ItemList::new(GameMode::into_enum_iter(), settings.mode as usize),
+            })
+        }
+        RecordsItem::Statistics => GameState::Statistics(StatisticsState {
+            menu,
+            stats: load_stats(),
+            page: 0,
+        }),
+        RecordsItem::Achievements => GameState::Achievements(AchievementsState { menu }),
+        RecordsItem::Back => GameState::StartMenu(*menu),
+    }
+}
+
+/// Flips through the high scores, the left and right arrow keys picking the
+/// mode and the up and down arrow keys picking the difficulty
+fn run_high_scores(state: HighScoresState, keyboard_handler: &dyn Keyboard) -> GameState {
+    let mut state = state;
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+        return GameState::Records(RecordsState::new(state.menu, RecordsItem::HighScores));
+    }
+    run_option_list(&mut state.mode_items, keyboard_handler);
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_UP) {
+        state.difficulty_items.move_back();
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_DOWN) {
+        state.difficulty_items.move_forward();
+    }
+    GameState::HighScores(state)
+}
+
+/// Flips through the pages of the statistics with the left and right arrow keys
+fn run_statistics(state: StatisticsState, keyboard_handler: &dyn Keyboard) -> GameState {
+    let mut state = state;
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+        return GameState::Records(RecordsState::new(state.menu, RecordsItem::Statistics));
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+        state.page = state.page.saturating_sub(1);
+    }
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
+        state.page = usize::min(state.page + 1, stats_pages(&state.stats).len() - 1);
+    }
+    GameState::Statistics(state)
+}
 
-    // turn sideways
-    if let Some(new_direction) = get_direction(&keyboard_handler) {
-        // make sure we're turning 90 degrees only, and not too often
-        if new_direction != snake.direction.opposite() && snake.turn_cooldown == 0 {
-            snake.direction = new_direction;
-            snake.movement_frames = snake.movement_period;
-            // add a delay to when next turn can happen, to prevent from moving
-            // very fast when moving in a diagonal
-            snake.turn_cooldown = snake.movement_period / 2;
-        }
+/// Waits for the players to continue to the next round, or to pick whether to
+/// play another match once the current match has been won.
+fn run_round_winner(state: RoundWinnerState, keyboard_handler: &dyn Keyboard) -> GameState {
+    if state.settings.network_player.is_some() {
+        return run_network_round_winner(state, keyboard_handler);
     }
 
-    // move snake body
-    if snake.movement_frames == snake.movement_period {
-        // check if about to hit a wall
-        let head_plus_one = snake.body.head() + snake.direction.unit();
-        if next_round.wall.collides_with_point(head_plus_one) {
-            next_round.game_over = true;
-            return next_round;
-        }
+    let match_over = state.match_score.match_winner().is_some();
+    let selection = if match_over {
+        run_game_over_selection(state.selection, keyboard_handler)
+    } else {
+        state.selection
+    };
 
-        snake.body.move_forward(snake.direction);
-        snake.movement_frames = 0;
+    if !keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+        return GameState::RoundWinner(RoundWinnerState {
+            selection,
+            ..state
+        });
     }
 
-    // check if collided with self
-    if snake.body.is_self_overlapping() {
-        next_round.game_over = true;
-        return next_round;
+    match (match_over, selection) {
+        (true, GameOverSelection::Exit) => GameState::ProgramExit(0),
+        (true, GameOverSelection::Restart) => GameState::RoundStart(RoundStartState {
+            frames: 0,
+            settings: state.settings,
+            match_score: new_match_score(state.settings),
+            new_game: true,
+        }),
+        (false, _) => GameState::RoundStart(RoundStartState {
+            frames: 0,
+            settings: state.settings,
+            match_score: Some(state.match_score),
+            new_game: false,
+        }),
     }
+}
 
-    // check if collision with apple
-    if snake.body.head() == round.apple {
-        // eat the apple
-        snake.body.extend_tail();
-        next_round.score += 100;
+/// Network matches move on to the next round by themselves, so that the two
+/// computers stay in step without having to wait for each other. Once the
+/// match is over the network connection is done with, so there's no restart.
+fn run_network_round_winner(
+    state: RoundWinnerState,
+    keyboard_handler: &dyn Keyboard,
+) -> GameState {
+    if state.match_score.match_winner().is_some() {
+        if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RETURN) {
+            GameState::ProgramExit(0)
+        } else {
+            GameState::RoundWinner(state)
+        }
+    } else if state.frames >= NETWORK_ROUND_WINNER_FRAMES {
+        GameState::RoundStart(RoundStartState {
+            frames: 0,
+            settings: state.settings,
+            match_score: Some(state.match_score),
+            new_game: false,
+        })
+    } else {
+        GameState::RoundWinner(RoundWinnerState {
+            frames: state.frames + 1,
+            ..state
+        })
+    }
+}
 
-        // make new apple
-        next_round.apple = generate_apple(ivec2_gen, &snake.body);
+/// Move the selection between the restart and exit options
+fn run_game_over_selection(
+    selection: GameOverSelection,
+    keyboard_handler: &dyn Keyboard,
+) -> GameOverSelection {
+    if keyboard_handler.key_pressed_now(virtual_keycodes::VK_RIGHT) {
+        GameOverSelection::Exit
+    } else if keyboard_handler.key_pressed_now(virtual_keycodes::VK_LEFT) {
+        GameOverSelection::Restart
+    } else {
+        selection
     }
+}
 
-    next_round
+/// A fresh match score when playing with several players, or `None` if there
+/// is no match to keep score of.
+fn new_match_score(settings: GameSettings) -> Option<MatchScore> {
+    match settings.players {
+        GamePlayers::One => None,
+        players => Some(MatchScore::new(players.count(), MATCH_WINS_NEEDED)),
+    }
 }
 
 fn run_round_ending(mut state: RoundEndState) -> RoundEndState {
@@ -534,61 +3133,154 @@ fn run_round_ending(mut state: RoundEndState) -> RoundEndState {
     let blink_period = 5;
     let elapsed_periods = state.frames / blink_period;
 
-    state.round.snake.color = if elapsed_periods < 8 {
-        /* Blink a few times */
-        if elapsed_periods % 2 == 0 {
-            88 // red
-        } else {
-            34 // green
+    for snake in &mut state.round.snakes {
+        snake.effects.clear();
+        if snake.alive {
+            continue;
         }
-    } else {
-        /* Remain red */
-        88
-    };
+
+        snake.color = if elapsed_periods < 8 {
+            /* Blink a few times */
+            if elapsed_periods.is_multiple_of(2) {
+                88 // red
+            } else {
+                snake.player_color
+            }
+        } else {
+            /* Remain red */
+            88
+        };
+    }
     state
 }
 
-fn draw_start_menu(menu_state: &StartMenuState, window: &pancurses::Window) {
-    let (mx, my) = graphics::screen_middle();
+fn draw_start_menu(menu_state: &StartMenuState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
     let attributes = {
-        let mut attributes = [attributes::A_NORMAL; 3];
+        let mut attributes = [attributes::A_NORMAL; StartMenuItem::VARIANT_COUNT];
         if menu_state.focused_area == StartMenuArea::Main {
-            attributes[menu_state.menu_items.current_index()] = attributes::A_REVERSE;
+            attributes[menu_state.menu_items.current_item() as usize] = attributes::A_REVERSE;
         }
         attributes
     };
 
     // let game_over = "Rust Snake";
     window.attron(pancurses::COLOR_PAIR(34));
-    draw_logo(window, mx - 29 / 2, my - 5);
+    draw_logo(window, mx - 29 / 2, my - 7);
     window.attroff(pancurses::COLOR_PAIR(34));
 
+    if menu_state.menu_items.contains(StartMenuItem::Continue) {
+        let continue_game = "Continue";
+        window.attron(attributes[StartMenuItem::Continue as usize]);
+        window.mvprintw(my - 2, mx - continue_game.len() as i32 / 2, continue_game);
+        window.attroff(attributes[StartMenuItem::Continue as usize]);
+    }
+
     let start_game = "Start";
     window.attron(attributes[StartMenuItem::Start as usize]);
-    window.mvprintw(my + 1, mx - start_game.len() as i32 / 2, start_game);
+    window.mvprintw(my - 1, mx - start_game.len() as i32 / 2, start_game);
     window.attroff(attributes[StartMenuItem::Start as usize]);
 
-    let difficulty = "Difficulty:";
-    window.attron(attributes[StartMenuItem::Difficulty as usize]);
-    window.mvprintw(my + 2, mx - 9, difficulty);
-    window.attroff(attributes[StartMenuItem::Difficulty as usize]);
+    draw_menu_setting(
+        window,
+        my,
+        "Players:",
+        &format!("{:?}", menu_state.settings.players),
+        attributes[StartMenuItem::Players as usize],
+        menu_state.focused_area == StartMenuArea::Players,
+    );
 
-    let difficulty_attr = if menu_state.focused_area == StartMenuArea::Difficulty {
-        attributes::A_REVERSE
-    } else {
-        attributes::A_NORMAL
-    };
-    window.attron(difficulty_attr);
-    window.mvprintw(my + 2, mx + 3, format!("{:?}", menu_state.difficulty));
-    window.attroff(difficulty_attr);
+    draw_menu_setting(
+        window,
+        my + 1,
+        "Rivals:",
+        &format!("{}", menu_state.settings.rivals),
+        attributes[StartMenuItem::Rivals as usize],
+        menu_state.focused_area == StartMenuArea::Rivals,
+    );
+
+    draw_menu_setting(
+        window,
+        my + 2,
+        "Difficulty:",
+        &format!("{:?}", menu_state.settings.difficulty),
+        attributes[StartMenuItem::Difficulty as usize],
+        menu_state.focused_area == StartMenuArea::Difficulty,
+    );
+
+    draw_menu_setting(
+        window,
+        my + 3,
+        "Mode:",
+        menu_state.settings.mode.name(),
+        attributes[StartMenuItem::Mode as usize],
+        menu_state.focused_area == StartMenuArea::Mode,
+    );
+
+    draw_menu_setting(
+        window,
+        my + 4,
+        "Level:",
+        &level::builtin(menu_state.settings.level).name,
+        attributes[StartMenuItem::Level as usize],
+        menu_state.focused_area == StartMenuArea::Level,
+    );
+
+    draw_menu_setting(
+        window,
+        my + 5,
+        "Bites:",
+        menu_state.settings.bite_rule.name(),
+        attributes[StartMenuItem::Bites as usize],
+        menu_state.focused_area == StartMenuArea::Bites,
+    );
+
+    draw_menu_setting(
+        window,
+        my + 6,
+        "Ghost:",
+        if menu_state.settings.ghost { "On" } else { "Off" },
+        attributes[StartMenuItem::Ghost as usize],
+        menu_state.focused_area == StartMenuArea::Ghost,
+    );
+
+    let records = "Records";
+    window.attron(attributes[StartMenuItem::Records as usize]);
+    window.mvprintw(my + 7, mx - records.len() as i32 / 2, records);
+    window.attroff(attributes[StartMenuItem::Records as usize]);
 
     let exit = "Exit";
     window.attron(attributes[StartMenuItem::Exit as usize]);
-    window.mvprintw(my + 3, mx - exit.len() as i32 / 2, exit);
+    window.mvprintw(my + 8, mx - exit.len() as i32 / 2, exit);
     window.attroff(attributes[StartMenuItem::Exit as usize]);
 }
 
-fn draw_logo(window: &pancurses::Window, x: i32, y: i32) {
+/// Draws a start menu setting as its `label` followed by its current `value`,
+/// where the value is highlighted when the setting is `focused`.
+fn draw_menu_setting(
+    window: &impl Canvas,
+    y: i32,
+    label: &str,
+    value: &str,
+    label_attr: pancurses::chtype,
+    focused: bool,
+) {
+    let (mx, _) = window.screen_middle();
+    window.attron(label_attr);
+    window.mvprintw(y, mx - 9, label);
+    window.attroff(label_attr);
+
+    let value_attr = if focused {
+        attributes::A_REVERSE
+    } else {
+        attributes::A_NORMAL
+    };
+    window.attron(value_attr);
+    window.mvprintw(y, mx + 3, value);
+    window.attroff(value_attr);
+}
+
+fn draw_logo(window: &impl Canvas, x: i32, y: i32) {
     // █████ █   █ █████ █   █ █████
     // █     ██  █ █   █ █  █  █
     // █████ █ █ █ █████ ███   █████
@@ -630,54 +3322,447 @@ fn draw_logo(window: &pancurses::Window, x: i32, y: i32) {
     window.draw_horizontal_line(y + 4, x + 25, 4);
 }
 
-fn draw_round_start(window: &pancurses::Window) {
-    let (mx, my) = graphics::screen_middle();
+fn draw_round_start(window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
     let get_ready = "Get Ready!";
     window.mvprintw(my, mx - get_ready.len() as i32 / 2, get_ready);
 }
 
-fn draw_ongoing_round(state: &RoundState, window: &pancurses::Window) {
+fn draw_ongoing_round(state: &RoundState, window: &impl Canvas) {
     draw_wall(&window, &state.wall);
-    draw_snake(&window, &state.snake);
-    draw_apple(&window, state.apple);
-    draw_score(&window, state.score);
+    if let Some(arena) = &state.arena {
+        draw_arena_warning(&window, arena);
+    }
+    draw_portals(&window, &state.level);
+    if let Some(ghost) = &state.ghost {
+        draw_ghost(&window, &ghost.round.snakes[0]);
+    }
+    // computer controlled snakes disappear when they die
+    for snake in state.snakes.iter().filter(|snake| snake.alive || !snake.is_ai()) {
+        draw_snake(&window, snake);
+    }
+    if state.has_items() {
+        draw_item(&window, &state.item);
+    }
+    draw_obstacles(&window, &state.obstacles);
+    let players: Vec<&SnakeState> = state.snakes.iter().filter(|snake| !snake.is_ai()).collect();
+    for (index, snake) in players.iter().enumerate() {
+        draw_snake_hud(&window, snake, index, players.len() > 1);
+    }
+    for snake in &state.snakes {
+        draw_score_popups(&window, &snake.scoring);
+    }
+    if let Some(clock) = &state.clock {
+        draw_clock(&window, clock);
+    }
+    if let Some(ghost) = &state.ghost {
+        draw_ghost_pace(&window, &state.snakes[0], &ghost.round.snakes[0]);
+    }
+}
+
+/// Draws the snake of the best round so far as a dim outline, for as long as
+/// it's still alive
+fn draw_ghost(window: &impl Canvas, ghost: &SnakeState) {
+    if !ghost.alive {
+        return;
+    }
+    window.attron(pancurses::COLOR_PAIR(GHOST_COLOR));
+    for point in ghost.body.points() {
+        let x = window.left_screen_margin() + 1 + point.x;
+        let y = window.top_screen_margin() + 1 + point.y;
+        window.mvprintw(y, x, ":");
+    }
+    window.attroff(pancurses::COLOR_PAIR(GHOST_COLOR));
+}
+
+/// Shows how far ahead of or behind the ghost the player is, in points, just
+/// below the right end of the play area
+fn draw_ghost_pace(window: &impl Canvas, player: &SnakeState, ghost: &SnakeState) {
+    let lead = player.scoring.total() as i64 - ghost.scoring.total() as i64;
+    let pace = format!("best {:+}", lead);
+    let color = if lead >= 0 {
+        34 // green
+    } else {
+        88 // red
+    };
+    let right = window.left_screen_margin() + graphics::BORDER_WIDTH;
+    let y = window.top_screen_margin() + graphics::BORDER_HEIGHT + 1;
+    window.attron(pancurses::COLOR_PAIR(color));
+    window.mvprintw(y, right - pace.len() as i32, pace);
+    window.attroff(pancurses::COLOR_PAIR(color));
+}
+
+/// Draws each pair of portals of the level in a color of its own
+fn draw_portals(window: &impl Canvas, level: &Level) {
+    for (pair, color) in level.portals.iter().zip(PORTAL_COLORS.iter().cycle()) {
+        window.attron(pancurses::COLOR_PAIR(*color));
+        for portal in pair {
+            let x = window.left_screen_margin() + 1 + portal.pos.x;
+            let y = window.top_screen_margin() + 1 + portal.pos.y;
+            window.mvprintw(y, x, "O");
+        }
+        window.attroff(pancurses::COLOR_PAIR(*color));
+    }
+}
+
+fn draw_obstacles(window: &impl Canvas, obstacles: &[Obstacle]) {
+    for obstacle in obstacles.iter().filter(|obstacle| obstacle.is_visible()) {
+        let color = if obstacle.is_debris() {
+            DEBRIS_COLOR
+        } else {
+            OBSTACLE_COLOR
+        };
+        window.attron(pancurses::COLOR_PAIR(color));
+        for cell in obstacle.cells() {
+            let x = window.left_screen_margin() + 1 + cell.x;
+            let y = window.top_screen_margin() + 1 + cell.y;
+            window.draw_horizontal_line(y, x, 1);
+        }
+        window.attroff(pancurses::COLOR_PAIR(color));
+    }
+}
+
+/// Blinks the wall that the arena of a survival round is about to close in to
+fn draw_arena_warning(window: &impl Canvas, arena: &survival::Arena) {
+    if !arena.warning_visible() {
+        return;
+    }
+    draw_rectilinear_line(window, &new_play_area_wall(arena.inset + 1), 88);
+
+    let (mx, _) = window.screen_middle();
+    let y = window.top_screen_margin() + graphics::BORDER_HEIGHT + 2;
+    let warning = "The walls are closing in!";
+    window.attron(pancurses::COLOR_PAIR(88));
+    window.mvprintw(y, mx - warning.len() as i32 / 2, warning);
+    window.attroff(pancurses::COLOR_PAIR(88));
+}
+
+/// Shows the time left of a time attack round below the play area, turning
+/// red when time is nearly up
+fn draw_clock(window: &impl Canvas, clock: &time_attack::Clock) {
+    let (mx, _) = window.screen_middle();
+    let y = window.top_screen_margin() + graphics::BORDER_HEIGHT + 1;
+    let time = format!("time: {}", clock.time_left_text());
+    let color = if clock.seconds_left() <= CLOCK_WARNING_SECONDS {
+        88 // red
+    } else {
+        1
+    };
+    window.attron(pancurses::COLOR_PAIR(color));
+    window.mvprintw(y, mx - time.len() as i32 / 2, time);
+    window.attroff(pancurses::COLOR_PAIR(color));
+}
+
+/// Draws the demo round with a blinking banner on top of it
+fn draw_attract_mode(state: &AttractModeState, window: &impl Canvas) {
+    draw_ongoing_round(&state.round, &window);
+
+    if (state.frames / ATTRACT_BANNER_BLINK_PERIOD).is_multiple_of(2) {
+        let (mx, my) = window.screen_middle();
+        let banner = "  PRESS ENTER  ";
+        window.attron(attributes::A_REVERSE);
+        window.mvprintw(my, mx - banner.len() as i32 / 2, banner);
+        window.attroff(attributes::A_REVERSE);
+    }
+}
+
+/// Draws the score, effects and speed of a snake. The first snake gets its
+/// status line above the play area, and the second snake below it.
+fn draw_snake_hud(window: &impl Canvas, snake: &SnakeState, index: usize, labeled: bool) {
+    let top = window.top_screen_margin();
+    let y = if index == 0 {
+        top - 2
+    } else {
+        top + graphics::BORDER_HEIGHT + 1
+    };
+    let label = if labeled {
+        format!("P{} ", index + 1)
+    } else {
+        String::new()
+    };
+
+    window.attron(pancurses::COLOR_PAIR(snake.player_color));
+    draw_score(&window, &snake.scoring, &label, y);
+    window.attroff(pancurses::COLOR_PAIR(snake.player_color));
+    draw_speed_level(&window, snake.speed_level(), y);
+    draw_effects(&window, &snake.effects, y);
+    if snake.autopilot {
+        draw_autopilot(&window, y);
+    }
+}
+
+/// Show that the autopilot is steering, just left of the speed level
+fn draw_autopilot(window: &impl Canvas, y: i32) {
+    let right = window.left_screen_margin() + graphics::BORDER_WIDTH - 1;
+    let autopilot = "autopilot";
+    window.attron(attributes::A_REVERSE);
+    window.mvprintw(y, right - 10 - autopilot.len() as i32, autopilot);
+    window.attroff(attributes::A_REVERSE);
+}
+
+fn draw_round_winner_screen(state: &RoundWinnerState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let match_winner = state.match_score.match_winner();
+
+    let title = match (match_winner, state.winner) {
+        (Some(player), _) => format!("Player {} wins the match!", player + 1),
+        (None, Some(player)) => format!("Player {} wins the round!", player + 1),
+        (None, None) => String::from("Draw!"),
+    };
+    let title_color = match state.winner {
+        Some(player) => PLAYER_COLORS[player],
+        None => 1,
+    };
+    window.attron(pancurses::COLOR_PAIR(title_color));
+    window.mvprintw(my - 4, mx - title.len() as i32 / 2, &title);
+    window.attroff(pancurses::COLOR_PAIR(title_color));
+
+    // show the round wins of each player side by side
+    let wins = &state.match_score.wins;
+    let mut x = mx - (wins.len() as i32 * 8) / 2;
+    for (player, player_wins) in wins.iter().enumerate() {
+        let label = format!("P{}: {}", player + 1, player_wins);
+        window.attron(pancurses::COLOR_PAIR(PLAYER_COLORS[player]));
+        window.mvprintw(my - 2, x, &label);
+        window.attroff(pancurses::COLOR_PAIR(PLAYER_COLORS[player]));
+        x += 8;
+    }
+
+    let first_to = format!("First to {} wins", state.match_score.wins_needed);
+    window.mvprintw(my - 1, mx - first_to.len() as i32 / 2, first_to);
+
+    if state.settings.network_player.is_some() {
+        let continue_text = if match_winner.is_none() {
+            "Next round starting..."
+        } else {
+            "Press Enter to exit"
+        };
+        window.mvprintw(my + 1, mx - continue_text.len() as i32 / 2, continue_text);
+        return;
+    }
+
+    if match_winner.is_none() {
+        let continue_text = "Press Enter to continue";
+        window.mvprintw(my + 1, mx - continue_text.len() as i32 / 2, continue_text);
+        return;
+    }
+
+    let attrs = match state.selection {
+        GameOverSelection::Restart => (attributes::A_REVERSE, attributes::A_NORMAL),
+        GameOverSelection::Exit => (attributes::A_NORMAL, attributes::A_REVERSE),
+    };
+
+    window.attron(attrs.0);
+    window.mvprintw(my + 1, mx - 7, "Restart");
+    window.attroff(attrs.0);
+
+    window.attron(attrs.1);
+    window.mvprintw(my + 1, mx + 3, "Exit");
+    window.attroff(attrs.1);
 }
 
-fn draw_game_over_screen(state: &GameOverState, window: &pancurses::Window) {
-    let (mx, my) = graphics::screen_middle();
+fn draw_game_over_screen(state: &GameOverState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
     let attrs = match state.selection {
         GameOverSelection::Restart => (attributes::A_REVERSE, attributes::A_NORMAL),
         GameOverSelection::Exit => (attributes::A_NORMAL, attributes::A_REVERSE),
     };
 
-    let game_over = "Game Over";
-    window.mvprintw(my - 4, mx - game_over.len() as i32 / 2, game_over);
+    let game_over = if state.board_cleared {
+        "Board Cleared!"
+    } else if state.outlasted_rivals {
+        "You Win!"
+    } else if state.clock.is_some() {
+        "Time's Up!"
+    } else {
+        "Game Over"
+    };
+    window.mvprintw(my - 6, mx - game_over.len() as i32 / 2, game_over);
+    if let Some(rank) = state.high_score {
+        let high_score = format!("New High Score! #{}", rank + 1);
+        window.attron(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+        window.mvprintw(my - 5, mx - high_score.len() as i32 / 2, high_score);
+        window.attroff(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+    }
+
+    let breakdown = [
+        ("Apples", state.breakdown.base),
+        ("Length bonus", state.breakdown.length_bonus),
+        ("Combo bonus", state.breakdown.combo_bonus),
+        ("Difficulty bonus", state.breakdown.difficulty_bonus),
+    ];
+    for (i, (label, points)) in breakdown.iter().enumerate() {
+        let y = my - 4 + i as i32;
+        window.mvprintw(y, mx - 11, format!("{}:", label));
+        window.mvprintw(y, mx + 5, format!("{:>6}", points));
+    }
+    if state.breakdown.penalty > 0 {
+        window.mvprintw(my, mx - 11, "Bite penalty:");
+        let penalty = format!("-{}", state.breakdown.penalty);
+        window.mvprintw(my, mx + 5, format!("{:>6}", penalty));
+    }
 
     let game_over = format!("Final Score: {}", state.final_score);
-    window.mvprintw(my - 1, mx - game_over.len() as i32 / 2, game_over);
+    window.mvprintw(my + 1, mx - game_over.len() as i32 / 2, game_over);
+
+    let mut options_y = my + 3;
+    if let Some(clock) = &state.clock {
+        let rate = format!("Apples per minute: {:.1}", clock.apples_per_minute());
+        window.mvprintw(my + 2, mx - rate.len() as i32 / 2, rate);
+        options_y += 1;
+    }
 
     window.attron(attrs.0);
-    window.mvprintw(my + 1, mx - 7, "Restart");
+    window.mvprintw(options_y, mx - 7, "Restart");
     window.attroff(attrs.0);
 
     window.attron(attrs.1);
-    window.mvprintw(my + 1, mx + 3, "Exit");
+    window.mvprintw(options_y, mx + 3, "Exit");
     window.attroff(attrs.1);
+
+    let seed = format!("Seed: {}", state.seed);
+    window.mvprintw(options_y + 2, mx - seed.len() as i32 / 2, seed);
+}
+
+fn draw_name_entry(state: &NameEntryState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let title = "New High Score!";
+    window.attron(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+    window.mvprintw(my - 3, mx - title.len() as i32 / 2, title);
+    window.attroff(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+    let score = format!("Score: {}", state.game_over.final_score);
+    window.mvprintw(my - 1, mx - score.len() as i32 / 2, score);
+
+    // the letters are spaced out, with the one being picked highlighted
+    let left = mx - high_scores::NAME_LENGTH as i32;
+    for (i, &letter) in state.name.iter().enumerate() {
+        let attr = if i == state.cursor {
+            attributes::A_REVERSE
+        } else {
+            attributes::A_NORMAL
+        };
+        window.attron(attr);
+        window.mvprintw(my + 1, left + 2 * i as i32, (letter as char).to_string());
+        window.attroff(attr);
+    }
+
+    let help = "up/down: letter  left/right: move  enter: done";
+    window.mvprintw(my + 3, mx - help.len() as i32 / 2, help);
+}
+
+fn draw_records(state: &RecordsState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let title = "Records";
+    window.mvprintw(my - 4, mx - title.len() as i32 / 2, title);
+    for (i, item) in RecordsItem::into_enum_iter().enumerate() {
+        let attr = if i == state.items.current_index() {
+            attributes::A_REVERSE
+        } else {
+            attributes::A_NORMAL
+        };
+        let name = item.name();
+        window.attron(attr);
+        window.mvprintw(my - 2 + i as i32, mx - name.len() as i32 / 2, name);
+        window.attroff(attr);
+    }
+}
+
+fn draw_high_scores(state: &HighScoresState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let difficulty = state.difficulty_items.current_item();
+    let mode = state.mode_items.current_item();
+    let title = format!("High Scores - {:?} {}", difficulty, mode.name());
+    window.attron(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+    window.mvprintw(my - 7, mx - title.len() as i32 / 2, title);
+    window.attroff(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR));
+
+    let key = (difficulty as u8, mode as u8);
+    let table = state.high_scores.table(key);
+    for rank in 0..high_scores::TABLE_SIZE {
+        let row = match table.get(rank) {
+            Some(entry) => format!(
+                "{:>2}. {} {:>8}",
+                rank + 1,
+                String::from_utf8_lossy(&entry.name),
+                entry.score
+            ),
+            None => format!("{:>2}. --- {:>8}", rank + 1, "-"),
+        };
+        window.mvprintw(my - 5 + rank as i32, mx - row.len() as i32 / 2, row);
+    }
+
+    let help = "left/right: mode  up/down: difficulty  enter: back";
+    window.mvprintw(my + 6, mx - help.len() as i32 / 2, help);
+}
+
+fn draw_statistics(state: &StatisticsState, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let pages = stats_pages(&state.stats);
+    let (title, rows) = &pages[state.page];
+    let title = format!("Statistics - {} ({}/{})", title, state.page + 1, pages.len());
+    window.mvprintw(my - 4, mx - title.len() as i32 / 2, title);
+    for (i, (label, value)) in rows.iter().enumerate() {
+        let y = my - 2 + i as i32;
+        window.mvprintw(y, mx - 12, format!("{}:", label));
+        window.mvprintw(y, mx + 12 - value.len() as i32, value);
+    }
+
+    let help = "left/right: page  enter: back";
+    window.mvprintw(my + 4, mx - help.len() as i32 / 2, help);
+}
+
+fn draw_achievements(unlocked: &Unlocked, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    let title = format!(
+        "Achievements ({}/{})",
+        unlocked.count(),
+        achievements::ACHIEVEMENTS.len()
+    );
+    window.mvprintw(my - 6, mx - title.len() as i32 / 2, title);
+
+    // locked achievements are drawn dimmed
+    for (i, achievement) in achievements::ACHIEVEMENTS.iter().enumerate() {
+        let y = my - 4 + i as i32;
+        let (mark, color) = if unlocked.contains(achievement) {
+            ("[x]", HIGH_SCORE_COLOR)
+        } else {
+            ("[ ]", GHOST_COLOR)
+        };
+        window.attron(pancurses::COLOR_PAIR(color));
+        window.mvprintw(y, mx - 26, format!("{} {}", mark, achievement.name));
+        window.mvprintw(y, mx - 7, achievement.description);
+        window.attroff(pancurses::COLOR_PAIR(color));
+    }
+
+    let help = "enter: back";
+    window.mvprintw(my + 6, mx - help.len() as i32 / 2, help);
+}
+
+/// Draws a banner announcing an achievement across the top of the play area
+fn draw_toast(toast: &Toast, window: &impl Canvas) {
+    let (mx, _) = window.screen_middle();
+    let text = format!(" Achievement unlocked: {} ", toast.achievement.name);
+    let y = window.top_screen_margin() + 1;
+    window.attron(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR) | attributes::A_REVERSE);
+    window.mvprintw(y, mx - text.len() as i32 / 2, text);
+    window.attroff(pancurses::COLOR_PAIR(HIGH_SCORE_COLOR) | attributes::A_REVERSE);
 }
 
-fn draw_snake(window: &pancurses::Window, snake: &SnakeState) {
-    draw_rectilinear_line(window, &snake.body, snake.color);
+fn draw_snake(window: &impl Canvas, snake: &SnakeState) {
+    for run in &snake.body.runs {
+        draw_rectilinear_line(window, run, snake.draw_color());
+    }
 }
 
-fn draw_wall(window: &pancurses::Window, wall: &RectilinearLine) {
+fn draw_wall(window: &impl Canvas, wall: &RectilinearLine) {
     draw_rectilinear_line(window, wall, 1);
 }
 
-fn draw_rectilinear_line(window: &pancurses::Window, line: &RectilinearLine, color: u64) {
+fn draw_rectilinear_line(window: &impl Canvas, line: &RectilinearLine, color: u64) {
     window.attron(pancurses::COLOR_PAIR(color));
 
-    let mut x = graphics::left_screen_margin() + 1 + line.start.x;
-    let mut y = graphics::top_screen_margin() + 1 + line.start.y;
+    let mut x = window.left_screen_margin() + 1 + line.start.x;
+    let mut y = window.top_screen_margin() + 1 + line.start.y;
 
     if line.len() == 1 {
         window.draw_horizontal_line(y, x, 1);
@@ -709,49 +3794,354 @@ fn draw_rectilinear_line(window: &pancurses::Window, line: &RectilinearLine, col
     window.attroff(pancurses::COLOR_PAIR(color));
 }
 
-fn draw_apple(window: &pancurses::Window, apple: IVec2) {
-    let x = graphics::left_screen_margin() + 1 + apple.x;
-    let y = graphics::top_screen_margin() + 1 + apple.y;
+fn draw_item(window: &impl Canvas, item: &BoardItem) {
+    if !item.is_visible() {
+        return;
+    }
+
+    let x = window.left_screen_margin() + 1 + item.pos.x;
+    let y = window.top_screen_margin() + 1 + item.pos.y;
+    let color = item.item.color();
 
-    window.attron(pancurses::COLOR_PAIR(88)); // red
+    window.attron(pancurses::COLOR_PAIR(color));
     window.draw_horizontal_line(y, x, 1);
-    window.attroff(pancurses::COLOR_PAIR(88)); // red
+    window.attroff(pancurses::COLOR_PAIR(color));
+}
+
+fn draw_score(window: &impl Canvas, scoring: &Scoring, label: &str, y: i32) {
+    let left = window.left_screen_margin();
+    let score = if scoring.multiplier > 1 {
+        format!("{}score: {} x{}", label, scoring.total(), scoring.multiplier)
+    } else {
+        format!("{}score: {}", label, scoring.total())
+    };
+    window.mvprintw(y, left, score);
+}
+
+/// Draw the points just scored next to where they were scored
+fn draw_score_popups(window: &impl Canvas, scoring: &Scoring) {
+    let top = window.top_screen_margin();
+    let left = window.left_screen_margin();
+    for popup in &scoring.popups {
+        let pos = popup.pos();
+        let x = left + 1 + pos.x - popup.text.len() as i32 / 2;
+        let y = top + pos.y;
+        window.attron(pancurses::COLOR_PAIR(220));
+        window.mvprintw(y, x, &popup.text);
+        window.attroff(pancurses::COLOR_PAIR(220));
+    }
+}
+
+fn draw_speed_level(window: &impl Canvas, level: usize, y: i32) {
+    let right = window.left_screen_margin() + graphics::BORDER_WIDTH - 1;
+    let speed = format!("speed: {}", level + 1);
+    window.mvprintw(y, right - speed.len() as i32, speed);
+}
+
+/// Lists the active effects along with how many seconds they have left
+fn draw_effects(window: &impl Canvas, effects: &ActiveEffects, y: i32) {
+    let (mx, _) = window.screen_middle();
+    let labels: Vec<(String, &effects::ActiveEffect)> = effects
+        .iter()
+        .map(|active| (format!("{} {}s", active.effect.name(), active.seconds_left()), active))
+        .collect();
+    let total_len = labels.iter().map(|(label, _)| label.len() + 1).sum::<usize>() as i32;
+
+    let mut x = mx - total_len / 2;
+    for (label, active) in labels {
+        let attr = if active.is_flashing() {
+            attributes::A_REVERSE
+        } else {
+            attributes::A_NORMAL
+        };
+        let color = pancurses::COLOR_PAIR(active.effect.color());
+        window.attron(color | attr);
+        window.mvprintw(y, x, &label);
+        window.attroff(color | attr);
+        x += label.len() as i32 + 1;
+    }
+}
+
+fn draw_network_error(message: &str, window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
+    window.mvprintw(my - 1, mx - message.len() as i32 / 2, message);
+    let exit_text = "Press Enter to exit";
+    window.mvprintw(my + 1, mx - exit_text.len() as i32 / 2, exit_text);
 }
 
-fn draw_score(window: &pancurses::Window, score: usize) {
-    let top = graphics::top_screen_margin();
-    let left = graphics::left_screen_margin();
-    window.mvprintw(top - 2, left, format!("score: {}", score));
+/// Draws the round being played back, with how far it has come and how to
+/// control the playback below it
+fn draw_playback(state: &PlaybackState, window: &impl Canvas) {
+    draw_ongoing_round(&state.round, &window);
+
+    let (mx, _) = window.screen_middle();
+    let y = window.top_screen_margin() + graphics::BORDER_HEIGHT + 2;
+    let time = |frames: u32| {
+        let seconds = frames / 60;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    let status = format!(
+        "replay {} / {}  {}x{}",
+        time(state.frame),
+        time(state.replay.frames),
+        PLAYBACK_SPEEDS[state.speed],
+        if state.paused { "  paused" } else { "" }
+    );
+    window.mvprintw(y, mx - status.len() as i32 / 2, status);
+    let help = "space: pause  s: step  1-4: speed  left/right: seek  esc: quit";
+    window.mvprintw(y + 1, mx - help.len() as i32 / 2, help);
+}
+
+fn draw_paused_round(state: &RoundState, window: &impl Canvas) {
+    draw_ongoing_round(state, &window);
+
+    let (mx, my) = window.screen_middle();
+    let paused = "Paused";
+    window.attron(attributes::A_REVERSE);
+    window.mvprintw(my, mx - paused.len() as i32 / 2, paused);
+    window.attroff(attributes::A_REVERSE);
+    let y = window.top_screen_margin() + graphics::BORDER_HEIGHT + 2;
+    let help = "p: continue  esc: save and quit";
+    window.mvprintw(y, mx - help.len() as i32 / 2, help);
 }
 
-fn draw_program_exit(window: &pancurses::Window) {
-    let (mx, my) = graphics::screen_middle();
+fn draw_program_exit(window: &impl Canvas) {
+    let (mx, my) = window.screen_middle();
     let good_bye = "Good Bye!";
     window.mvprintw(my, mx - good_bye.len() as i32 / 2, good_bye);
 }
 
-/// Creates a new apple using `generator`, while avoiding having it overlapping
-/// with the `snake_body`
-fn generate_apple(generator: &mut IVec2Generator, snake_body: &RectilinearLine) -> IVec2 {
-    loop {
-        let point = generator.gen_ivec2();
-        if snake_body.collides_with_point(point) {
-            continue;
-        } else {
-            break point;
-        }
+/// Whether an item can be placed at `point` as far as the arena, the portals
+/// and the obstacles of a round go
+fn is_open_for_item(
+    point: IVec2,
+    arena: Option<&survival::Arena>,
+    level: &Level,
+    obstacles: &[Obstacle],
+) -> bool {
+    arena.is_none_or(|arena| arena.contains(point))
+        && !level.is_portal(point)
+        && !obstacles.iter().any(|obstacle| {
+            obstacle
+                .upcoming_cells(OBSTACLE_ITEM_CLEARANCE)
+                .contains(&point)
+        })
+}
+
+/// Creates a new random item using `generator`, while avoiding having it
+/// overlapping with any of the `snakes` or the cells that aren't `is_open`.
+/// Returns `None` if the snakes cover the whole play area.
+fn generate_item(
+    generator: &mut IVec2Generator,
+    snakes: &[SnakeState],
+    is_open: impl Fn(IVec2) -> bool,
+) -> Option<BoardItem> {
+    let item = generator.gen_item();
+    let is_free = |point| {
+        is_open(point) && !snakes.iter().any(|snake| snake.body.collides_with_point(point))
+    };
+    let random_pos = (0..ITEM_PLACEMENT_ATTEMPTS)
+        .map(|_| generator.gen_ivec2())
+        .find(|&point| is_free(point));
+    let pos = match random_pos {
+        Some(pos) => pos,
+        // the board is nearly full, so pick among the cells that are left
+        None => items::pick_free_cell(
+            &mut generator.rng,
+            graphics::BORDER_WIDTH - 2,
+            graphics::BORDER_HEIGHT - 2,
+            is_free,
+        )?,
+    };
+    Some(BoardItem::new(item, pos))
+}
+
+/// Replaces the item on the board with a new one, or marks the board as
+/// cleared if there's no room left for one.
+fn replace_item(round: &mut RoundState, generator: &mut IVec2Generator) {
+    let is_open =
+        |point| is_open_for_item(point, round.arena.as_ref(), &round.level, &round.obstacles);
+    match generate_item(generator, &round.snakes, is_open) {
+        Some(item) => round.item = item,
+        None => round.board_cleared = true,
     }
 }
 
-/// Create the wall that surrounds the play area
-fn new_play_area_wall() -> RectilinearLine {
+/// A snake body of `len` cells heading down from the top of the play area at `x`
+fn new_snake_body(x: i32, len: usize) -> SnakeBody {
+    SnakeBody::new(RectilinearLine {
+        start: i32::ivec2(x, 0),
+        segments: VecDeque::from(vec![seg!(Direction::Down, len - 1)]),
+    })
+}
+
+/// Create the wall that surrounds the play area, moved in by `inset` cells
+/// from every side
+fn new_play_area_wall(inset: i32) -> RectilinearLine {
+    let width = (graphics::BORDER_WIDTH - 2 * inset) as usize;
+    let height = (graphics::BORDER_HEIGHT - 2 * inset) as usize;
     RectilinearLine {
-        start: i32::ivec2(-1, -1), // we surround the play area, so we start at (-1,-1)
+        // we surround the play area, so we start at (-1,-1)
+        start: i32::ivec2(inset - 1, inset - 1),
         segments: VecDeque::from(vec![
-            seg!(Direction::Right, graphics::BORDER_WIDTH as usize - 1),
-            seg!(Direction::Down, graphics::BORDER_HEIGHT as usize - 1),
-            seg!(Direction::Left, graphics::BORDER_WIDTH as usize - 1),
-            seg!(Direction::Up, graphics::BORDER_HEIGHT as usize - 2),
+            seg!(Direction::Right, width - 1),
+            seg!(Direction::Down, height - 1),
+            seg!(Direction::Left, width - 1),
+            seg!(Direction::Up, height - 2),
         ]),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: GameMode, bite_rule: BiteRule) -> GameSettings {
+        GameSettings {
+            difficulty: GameDifficulty::Normal,
+            mode,
+            level: 0,
+            bite_rule,
+            ghost: false,
+            players: GamePlayers::One,
+            rivals: 0,
+            network_player: None,
+            seed: None,
+        }
+    }
+
+    /// A single player round, with the item moved out of the way
+    fn player_round(settings: GameSettings, generator: &mut IVec2Generator) -> RoundState {
+        let controllers = vec![Controller::Keyboard(ARROW_KEYS)];
+        let mut round = RoundState::with_controllers(generator, controllers, settings, None);
+        round.item.pos = i32::ivec2(40, 15);
+        round
+    }
+
+    #[test]
+    fn replay_leads_back_to_the_round_played() {
+        let settings = GameSettings {
+            rivals: 1,
+            ..settings(GameMode::Classic, BiteRule::Crash)
+        };
+        let mut generator = IVec2Generator::new(0);
+        let mut round = RoundState::with_seed(&mut generator, 7, settings, None);
+        let mut replay = Replay::new(replay_setup(settings, 7));
+        for frame in 0..300 {
+            let autopilot_toggled = frame == 0;
+            if autopilot_toggled {
+                toggle_autopilot(&mut round);
+            }
+            replay.record(&[None, None], autopilot_toggled);
+            round = run_ongoing_round(round, &[None, None], &mut generator);
+        }
+
+        let playback = seek_replay(PlaybackState::new(replay, settings), 300);
+        assert_eq!(round_state_hash(&playback.round), round_state_hash(&round));
+    }
+
+    #[test]
+    fn autopilot_counts_as_used_after_turning_it_off() {
+        let mut generator = IVec2Generator::new(0);
+        let mut round = player_round(settings(GameMode::Classic, BiteRule::Crash), &mut generator);
+        assert!(!round.snakes[0].used_autopilot);
+        toggle_autopilot(&mut round);
+        toggle_autopilot(&mut round);
+        assert!(!round.snakes[0].autopilot);
+        assert!(round.snakes[0].used_autopilot);
+    }
+
+    #[test]
+    fn going_straight_only_counts_in_a_won_round() {
+        let mut generator = IVec2Generator::new(0);
+        let mut round = player_round(settings(GameMode::Classic, BiteRule::Crash), &mut generator);
+        round.replay = Some(Replay::new(replay_setup(round.settings, 0)));
+        round.snakes[0].longest_straight = 20 * 60;
+        let won_straight = |round: &RoundState| {
+            achievement_events(round)
+                .iter()
+                .any(|event| matches!(event, achievements::Event::WonStraight(_)))
+        };
+        assert!(!won_straight(&round));
+        round.game_over = true;
+        assert!(!won_straight(&round));
+        round.board_cleared = true;
+        assert!(won_straight(&round));
+    }
+
+    #[test]
+    fn autopilot_snakes_earn_no_achievements() {
+        let mut generator = IVec2Generator::new(0);
+        let mut round = player_round(settings(GameMode::Classic, BiteRule::Crash), &mut generator);
+        round.replay = Some(Replay::new(replay_setup(round.settings, 0)));
+        assert!(!achievement_events(&round).is_empty());
+        toggle_autopilot(&mut round);
+        toggle_autopilot(&mut round);
+        assert!(achievement_events(&round).is_empty());
+    }
+
+    #[test]
+    fn snake_survives_the_debris_of_biting_itself() {
+        let mut generator = IVec2Generator::new(0);
+        let mut round = player_round(settings(GameMode::Classic, BiteRule::Debris), &mut generator);
+        // a loop with the head right below the second cell of the snake
+        round.snakes[0].body = SnakeBody::new(RectilinearLine {
+            start: i32::ivec2(5, 3),
+            segments: VecDeque::from(vec![
+                seg!(Direction::Down, 2),
+                seg!(Direction::Right, 3),
+                seg!(Direction::Down, 2),
+                seg!(Direction::Left, 3),
+                seg!(Direction::Up, 1),
+            ]),
+        });
+        round.snakes[0].direction = Direction::Up;
+        let length = round.snakes[0].body.len();
+
+        let round = run_ongoing_round(round, &[Some(Direction::Up)], &mut generator);
+        assert_eq!(round.snakes[0].body.len(), length - 2);
+        assert_eq!(round.obstacles.len(), 1);
+        assert!(!round.obstacles[0].covers(round.snakes[0].body.head()));
+
+        let round = run_ongoing_round(round, &[None], &mut generator);
+        assert!(round.snakes[0].alive);
+    }
+
+    #[test]
+    fn snake_respawns_clear_of_other_snakes() {
+        let mut generator = IVec2Generator::new(0);
+        let settings = settings(GameMode::TimeAttack, BiteRule::Crash);
+        let mut round = player_round(settings, &mut generator);
+        // a rival sitting right where the player would respawn
+        let middle = (graphics::BORDER_WIDTH - 2) / 2;
+        let controller = Controller::Ai(AiPersonality::Easy);
+        let rival = SnakeState::new(middle, 0, controller, GameDifficulty::Normal);
+        round.snakes.push(rival);
+        round.snakes[0].alive = false;
+
+        let round = run_ongoing_round(round, &[None, None], &mut generator);
+        assert!(round.snakes[0].alive);
+        assert_eq!(round.snakes[0].body.head().x, middle - 1);
+        assert_eq!(round.clock.map(|clock| clock.deaths), Some(1));
+    }
+
+    #[test]
+    fn tail_stays_put_while_growing() {
+        let controller = Controller::Keyboard(ARROW_KEYS);
+        let mut snake = SnakeState::new(5, 0, controller, GameDifficulty::Normal);
+        let level = level::builtin(0);
+        let tail = snake.body.tail();
+        snake.pending_growth = 2;
+        for length in [5, 6] {
+            snake.movement_progress = snake.movement_period();
+            move_snake(&mut snake, None, &level, |_| false, false);
+            assert_eq!(snake.body.tail(), tail);
+            assert_eq!(snake.body.len(), length);
+        }
+        snake.movement_progress = snake.movement_period();
+        move_snake(&mut snake, None, &level, |_| false, false);
+        assert_eq!(snake.body.tail(), tail + Direction::Down.unit());
+        assert_eq!(snake.body.len(), 6);
+        assert_eq!(snake.pending_growth, 0);
+    }
+}
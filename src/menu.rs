@@ -35,6 +35,13 @@ impl<T: Copy> ItemList<T> {
     pub fn current_index(&self) -> usize {
         self.index
     }
+
+    pub fn contains(&self, item: T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.items.contains(&item)
+    }
 }
 
 #[cfg(test)]
@@ -78,4 +85,11 @@ mod tests {
         list.move_forward(); // C
         assert_eq!(list.current_item(), TestEnum::C);
     }
+
+    #[test]
+    fn contains_only_listed_items() {
+        let list = ItemList::new([1, 3].iter().copied(), 0);
+        assert!(list.contains(3));
+        assert!(!list.contains(2));
+    }
 }
@@ -0,0 +1,291 @@
+use crate::binary;
+use crate::rectilinear::Direction;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Bumped whenever the messages change, so that different versions of the
+/// game refuse to play each other instead of going out of sync
+const PROTOCOL_VERSION: u8 = 2;
+
+/// How long to wait for the other computer before giving up on it
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const HELLO_TAG: u8 = 0;
+const INPUT_TAG: u8 = 1;
+
+/// What both computers need to agree on before a network match can start
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MatchSetup {
+    pub seed: u64,      // for the random number generator
+    pub difficulty: u8, // index of the difficulty in the start menu
+}
+
+/// The messages sent between the two computers in a network match
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Message {
+    /// Sent by the host as soon as the other player has joined
+    Hello { version: u8, setup: MatchSetup },
+    /// The input of a player for one tick, along with a hash of the round
+    /// state that the tick starts from
+    Input {
+        tick: u32,
+        direction: Option<Direction>,
+        state_hash: u64,
+    },
+}
+
+impl Message {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        match self {
+            Message::Hello { version, setup } => {
+                bytes.push(HELLO_TAG);
+                bytes.push(*version);
+                bytes.extend_from_slice(&setup.seed.to_le_bytes());
+                bytes.push(setup.difficulty);
+            }
+            Message::Input {
+                tick,
+                direction,
+                state_hash,
+            } => {
+                bytes.push(INPUT_TAG);
+                bytes.extend_from_slice(&tick.to_le_bytes());
+                bytes.push(binary::encode_direction(*direction));
+                bytes.extend_from_slice(&state_hash.to_le_bytes());
+            }
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match binary::read_bytes::<_, 1>(reader)?[0] {
+            HELLO_TAG => {
+                let version = binary::read_bytes::<_, 1>(reader)?[0];
+                let seed = u64::from_le_bytes(binary::read_bytes(reader)?);
+                let difficulty = binary::read_bytes::<_, 1>(reader)?[0];
+                Ok(Message::Hello {
+                    version,
+                    setup: MatchSetup { seed, difficulty },
+                })
+            }
+            INPUT_TAG => {
+                let tick = u32::from_le_bytes(binary::read_bytes(reader)?);
+                let direction = binary::decode_direction(binary::read_bytes::<_, 1>(reader)?[0])?;
+                let state_hash = u64::from_le_bytes(binary::read_bytes(reader)?);
+                Ok(Message::Input {
+                    tick,
+                    direction,
+                    state_hash,
+                })
+            }
+            tag => Err(binary::invalid_data(format!("unknown message tag {}", tag))),
+        }
+    }
+}
+
+/// Everything that can go wrong in a network match
+#[derive(Debug)]
+pub enum NetError {
+    Disconnected,
+    Io(io::Error),
+    VersionMismatch { ours: u8, theirs: u8 },
+    UnexpectedMessage(Message),
+    Desync { tick: u32 },
+}
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => NetError::Disconnected,
+            _ => NetError::Io(error),
+        }
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Disconnected => write!(f, "The other player left"),
+            NetError::Io(error) => write!(f, "Network error: {}", error),
+            NetError::VersionMismatch { ours, theirs } => write!(
+                f,
+                "Incompatible game versions (ours {}, theirs {})",
+                ours, theirs
+            ),
+            NetError::UnexpectedMessage(message) => write!(f, "Unexpected message {:?}", message),
+            NetError::Desync { tick } => write!(f, "Games went out of sync on tick {}", tick),
+        }
+    }
+}
+
+/// A connection to the other computer in a network match, which is played in
+/// lockstep: both computers run the exact same simulation, and each tick only
+/// the inputs of the players are sent between them.
+#[derive(Debug)]
+pub struct Lockstep {
+    stream: TcpStream,
+    tick: u32,
+}
+
+impl Lockstep {
+    fn new(stream: TcpStream) -> Result<Self, NetError> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        Ok(Lockstep { stream, tick: 0 })
+    }
+
+    /// Sends the `input` of the local player for the current tick along with
+    /// the hash of the current state, and waits for the input of the remote
+    /// player for the same tick. Fails if the hashes don't match, since then
+    /// the two simulations have drifted apart.
+    pub fn exchange(
+        &mut self,
+        input: Option<Direction>,
+        state_hash: u64,
+    ) -> Result<Option<Direction>, NetError> {
+        let tick = self.tick;
+        Message::Input {
+            tick,
+            direction: input,
+            state_hash,
+        }
+        .write_to(&mut self.stream)?;
+
+        match Message::read_from(&mut self.stream)? {
+            Message::Input {
+                tick: their_tick,
+                direction,
+                state_hash: their_hash,
+            } if their_tick == tick => {
+                if their_hash != state_hash {
+                    return Err(NetError::Desync { tick });
+                }
+                self.tick += 1;
+                Ok(direction)
+            }
+            message => Err(NetError::UnexpectedMessage(message)),
+        }
+    }
+}
+
+/// Waits for another player to join on `port`, and tells them how the match
+/// is set up.
+pub fn host(port: u16, setup: MatchSetup) -> Result<Lockstep, NetError> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let (stream, _) = listener.accept()?;
+    let mut lockstep = Lockstep::new(stream)?;
+    Message::Hello {
+        version: PROTOCOL_VERSION,
+        setup,
+    }
+    .write_to(&mut lockstep.stream)?;
+    Ok(lockstep)
+}
+
+/// Joins the match hosted at `address`, and finds out how it's set up
+pub fn join(address: &str) -> Result<(Lockstep, MatchSetup), NetError> {
+    let stream = TcpStream::connect(address)?;
+    let mut lockstep = Lockstep::new(stream)?;
+    match Message::read_from(&mut lockstep.stream)? {
+        Message::Hello { version, setup } if version == PROTOCOL_VERSION => Ok((lockstep, setup)),
+        Message::Hello { version, .. } => Err(NetError::VersionMismatch {
+            ours: PROTOCOL_VERSION,
+            theirs: version,
+        }),
+        message => Err(NetError::UnexpectedMessage(message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::thread;
+
+    fn round_trip(message: Message) -> Message {
+        let mut bytes = Vec::new();
+        message.write_to(&mut bytes).unwrap();
+        Message::read_from(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    /// Connects two lockstep sessions to each other over localhost
+    fn connected_pair() -> (Lockstep, Lockstep) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || TcpStream::connect(address).unwrap());
+        let (server_stream, _) = listener.accept().unwrap();
+        let client_stream = client.join().unwrap();
+        (
+            Lockstep::new(server_stream).unwrap(),
+            Lockstep::new(client_stream).unwrap(),
+        )
+    }
+
+    #[test]
+    fn messages_survive_round_trip() {
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            setup: MatchSetup {
+                seed: 0x0123_4567_89ab_cdef,
+                difficulty: 2,
+            },
+        };
+        assert_eq!(round_trip(hello), hello);
+
+        for &direction in &[None, Some(Direction::Left), Some(Direction::Down)] {
+            let input = Message::Input {
+                tick: 1234,
+                direction,
+                state_hash: u64::MAX,
+            };
+            assert_eq!(round_trip(input), input);
+        }
+    }
+
+    #[test]
+    fn reading_garbage_is_an_error() {
+        let mut reader = Cursor::new(vec![INPUT_TAG, 0, 0, 0, 0, 9]);
+        assert!(Message::read_from(&mut reader).is_err());
+        let mut reader = Cursor::new(vec![0xff]);
+        assert!(Message::read_from(&mut reader).is_err());
+    }
+
+    #[test]
+    fn peers_receive_each_others_input() {
+        let (mut host, mut client) = connected_pair();
+        let remote = thread::spawn(move || client.exchange(Some(Direction::Up), 42).unwrap());
+        let host_received = host.exchange(Some(Direction::Left), 42).unwrap();
+        let client_received = remote.join().unwrap();
+
+        assert_eq!(host_received, Some(Direction::Up));
+        assert_eq!(client_received, Some(Direction::Left));
+        assert_eq!(host.tick, 1);
+    }
+
+    #[test]
+    fn differing_state_hashes_are_a_desync() {
+        let (mut host, mut client) = connected_pair();
+        let remote = thread::spawn(move || client.exchange(None, 1).is_err());
+        let result = host.exchange(None, 2);
+
+        assert!(matches!(result, Err(NetError::Desync { tick: 0 })));
+        assert!(remote.join().unwrap());
+    }
+
+    #[test]
+    fn closed_connection_means_other_player_left() {
+        let (mut host, client) = connected_pair();
+        drop(client);
+        let result = host.exchange(None, 0);
+        assert!(matches!(result, Err(NetError::Disconnected)));
+    }
+}
@@ -0,0 +1,231 @@
+use glam::i32;
+use glam::IVec2;
+
+/// The ways a bar points as it turns, a quarter of the way round at a time
+const BAR_ARMS: [(i32, i32); 4] = [(1, 0), (1, 1), (0, 1), (-1, 1)];
+
+/// Number of frames before debris crumbles away that it starts blinking
+const CRUMBLE_WARNING_FRAMES: usize = 2 * 60;
+
+/// Number of frames between each blink of debris about to crumble away
+const CRUMBLE_BLINK_PERIOD: usize = 6;
+
+/// How an obstacle moves around the play area
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motion {
+    /// A block sliding through a loop of cells, one cell each step
+    Slide(Vec<IVec2>),
+    /// A bar turning around its middle, with `arm` cells on either side of it
+    Rotate { pivot: IVec2, arm: i32 },
+    /// Cells that stay where they are
+    Still(Vec<IVec2>),
+}
+
+/// Something in the way of the snakes that keeps moving along a looped path,
+/// taking one step every `period` frames. Debris left behind by a snake is an
+/// obstacle too, one that doesn't move but crumbles away after a while.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obstacle {
+    pub motion: Motion,
+    pub period: usize,              // frames
+    pub pose: usize,                // how far along its loop the obstacle has come
+    pub frames_left: Option<usize>, // until debris crumbles away
+    frames_until_step: usize,
+}
+
+impl Obstacle {
+    pub fn new(motion: Motion, period: usize) -> Self {
+        Obstacle {
+            motion,
+            period,
+            pose: 0,
+            frames_left: None,
+            frames_until_step: period,
+        }
+    }
+
+    /// Debris covering `cells`, which crumbles away after `frames`
+    pub fn debris(cells: Vec<IVec2>, frames: usize) -> Self {
+        Obstacle {
+            frames_left: Some(frames),
+            ..Obstacle::new(Motion::Still(cells), 1)
+        }
+    }
+
+    pub fn is_debris(&self) -> bool {
+        self.frames_left.is_some()
+    }
+
+    pub fn has_crumbled(&self) -> bool {
+        self.frames_left == Some(0)
+    }
+
+    /// Debris blinks when it's about to crumble away
+    pub fn is_visible(&self) -> bool {
+        match self.frames_left {
+            Some(frames) if frames < CRUMBLE_WARNING_FRAMES => {
+                (frames / CRUMBLE_BLINK_PERIOD).is_multiple_of(2)
+            }
+            _ => true,
+        }
+    }
+
+    /// Number of steps it takes the obstacle to get back to where it started
+    pub fn poses(&self) -> usize {
+        match &self.motion {
+            Motion::Slide(path) => path.len(),
+            Motion::Rotate { .. } => BAR_ARMS.len(),
+            Motion::Still(_) => 1,
+        }
+    }
+
+    fn cells_in_pose(&self, pose: usize) -> Vec<IVec2> {
+        let pose = pose % self.poses();
+        match &self.motion {
+            Motion::Slide(path) => vec![path[pose]],
+            Motion::Rotate { pivot, arm } => {
+                let (x, y) = BAR_ARMS[pose];
+                (-arm..=*arm)
+                    .map(|step| *pivot + i32::ivec2(x, y) * step)
+                    .collect()
+            }
+            Motion::Still(cells) => cells.clone(),
+        }
+    }
+
+    /// The cells the obstacle covers right now
+    pub fn cells(&self) -> Vec<IVec2> {
+        self.cells_in_pose(self.pose)
+    }
+
+    pub fn covers(&self, point: IVec2) -> bool {
+        self.cells().contains(&point)
+    }
+
+    /// The cells the obstacle covers now and over its next `steps` steps
+    pub fn upcoming_cells(&self, steps: usize) -> Vec<IVec2> {
+        (self.pose..=self.pose + steps)
+            .flat_map(|pose| self.cells_in_pose(pose))
+            .collect()
+    }
+
+    /// Counts down to the next step, and takes it when it's time to
+    pub fn tick(&mut self) {
+        if let Some(frames_left) = &mut self.frames_left {
+            *frames_left = frames_left.saturating_sub(1);
+        }
+        self.frames_until_step -= 1;
+        if self.frames_until_step == 0 {
+            self.pose = (self.pose + 1) % self.poses();
+            self.frames_until_step = self.period;
+        }
+    }
+}
+
+/// The loop of cells a sliding block goes through when going from corner to
+/// corner, and then from the last corner back to the first. Returns `None` if
+/// two corners in a row aren't lined up horizontally or vertically.
+pub fn slide_path(corners: &[IVec2]) -> Option<Vec<IVec2>> {
+    let mut path = Vec::new();
+    for (index, &from) in corners.iter().enumerate() {
+        let to = corners[(index + 1) % corners.len()];
+        if from.x != to.x && from.y != to.y {
+            return None;
+        }
+        let step = (to - from).signum();
+        let mut cell = from;
+        while cell != to {
+            path.push(cell);
+            cell += step;
+        }
+    }
+    if path.is_empty() {
+        // the block stays put
+        path.extend(corners.first());
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_block_goes_back_and_forth_between_two_corners() {
+        let path = slide_path(&[i32::ivec2(0, 0), i32::ivec2(2, 0)]).unwrap();
+        let expected = vec![
+            i32::ivec2(0, 0),
+            i32::ivec2(1, 0),
+            i32::ivec2(2, 0),
+            i32::ivec2(1, 0),
+        ];
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn sliding_block_needs_straight_lines() {
+        assert_eq!(slide_path(&[i32::ivec2(0, 0), i32::ivec2(2, 2)]), None);
+        assert_eq!(
+            slide_path(&[i32::ivec2(4, 4)]),
+            Some(vec![i32::ivec2(4, 4)])
+        );
+    }
+
+    #[test]
+    fn obstacle_steps_once_every_period() {
+        let path = slide_path(&[i32::ivec2(0, 0), i32::ivec2(0, 1)]).unwrap();
+        let mut obstacle = Obstacle::new(Motion::Slide(path), 3);
+        obstacle.tick();
+        obstacle.tick();
+        assert_eq!(obstacle.cells(), vec![i32::ivec2(0, 0)]);
+        obstacle.tick();
+        assert_eq!(obstacle.cells(), vec![i32::ivec2(0, 1)]);
+        for _ in 0..3 {
+            obstacle.tick();
+        }
+        assert_eq!(obstacle.pose, 0);
+    }
+
+    #[test]
+    fn bar_turns_around_its_pivot() {
+        let motion = Motion::Rotate {
+            pivot: i32::ivec2(5, 5),
+            arm: 1,
+        };
+        let mut bar = Obstacle::new(motion, 1);
+        assert_eq!(
+            bar.cells(),
+            vec![i32::ivec2(4, 5), i32::ivec2(5, 5), i32::ivec2(6, 5)]
+        );
+        bar.tick();
+        bar.tick();
+        assert_eq!(
+            bar.cells(),
+            vec![i32::ivec2(5, 4), i32::ivec2(5, 5), i32::ivec2(5, 6)]
+        );
+        assert!(bar.covers(i32::ivec2(5, 4)));
+        assert!(!bar.covers(i32::ivec2(4, 5)));
+    }
+
+    #[test]
+    fn debris_stays_put_until_it_crumbles() {
+        let cells = vec![i32::ivec2(1, 1), i32::ivec2(1, 2)];
+        let mut debris = Obstacle::debris(cells.clone(), 2);
+        debris.tick();
+        assert_eq!(debris.cells(), cells);
+        assert!(!debris.has_crumbled());
+        debris.tick();
+        assert!(debris.has_crumbled());
+    }
+
+    #[test]
+    fn upcoming_cells_include_the_next_steps() {
+        let path = slide_path(&[i32::ivec2(0, 0), i32::ivec2(9, 0)]).unwrap();
+        let block = Obstacle::new(Motion::Slide(path), 10);
+        let upcoming = block.upcoming_cells(2);
+        assert_eq!(
+            upcoming,
+            vec![i32::ivec2(0, 0), i32::ivec2(1, 0), i32::ivec2(2, 0)]
+        );
+    }
+}
@@ -112,6 +112,19 @@ impl RectilinearLine {
         pos
     }
 
+    /// Every point covered by the line, from the start to the head
+    pub fn points(&self) -> Vec<IVec2> {
+        let mut pos = self.start;
+        let mut points = vec![pos];
+        for segment in &self.segments {
+            for _ in 0..segment.len {
+                pos += segment.dir.unit();
+                points.push(pos);
+            }
+        }
+        points
+    }
+
     pub fn shrink_tail(&mut self) {
         let tail = &mut self.segments[0];
         self.start += tail.dir.unit();
@@ -123,28 +136,49 @@ impl RectilinearLine {
         }
     }
 
+    /// Moves the head one step in `dir`. A line that is only a single point
+    /// gets its first segment.
     pub fn extend_head(&mut self, dir: Direction) {
-        let last_index = self.segments.len() - 1;
-        let last_segment = &mut self.segments[last_index];
-
-        if last_segment.dir == dir {
-            last_segment.len += 1;
-        } else {
-            self.segments.push_back(ChainedLineSegment { dir, len: 1 });
+        match self.segments.back_mut() {
+            Some(last_segment) if last_segment.dir == dir => last_segment.len += 1,
+            _ => self.segments.push_back(ChainedLineSegment { dir, len: 1 }),
         }
     }
 
-    pub fn extend_tail(&mut self) {
-        let first_seg = &mut self.segments[0];
-        first_seg.len += 1;
-        self.start += first_seg.dir.opposite().unit();
-    }
-
     pub fn move_forward(&mut self, dir: Direction) {
         self.shrink_tail();
         self.extend_head(dir);
     }
 
+    /// Splits the line in two right after the first time it passes through
+    /// `point`, returning the part up to and including `point`, and the part
+    /// that comes after it. Returns `None` if the line doesn't pass through
+    /// `point`, or if there's nothing after it.
+    pub fn split_at(&self, point: IVec2) -> Option<(RectilinearLine, RectilinearLine)> {
+        let distance = self.points().iter().position(|&pos| pos == point)?;
+        let mut steps = self
+            .segments
+            .iter()
+            .flat_map(|segment| std::iter::repeat_n(segment.dir, segment.len));
+
+        let mut before = RectilinearLine {
+            start: self.start,
+            segments: VecDeque::new(),
+        };
+        for dir in steps.by_ref().take(distance) {
+            before.extend_head(dir);
+        }
+
+        let mut after = RectilinearLine {
+            start: point + steps.next()?.unit(),
+            segments: VecDeque::new(),
+        };
+        for dir in steps {
+            after.extend_head(dir);
+        }
+        Some((before, after))
+    }
+
     /// Checks if any segment overlaps itself anywhere except in the points
     /// where the line segments join together to make up the line
     pub fn is_self_overlapping(&self) -> bool {
@@ -214,6 +248,11 @@ impl RectilinearLine {
     }
 
     pub fn collides_with_point(&self, point: IVec2) -> bool {
+        // a line without segments is just its start point
+        if self.segments.is_empty() {
+            return point == self.start;
+        }
+
         /* Check horizontal collisions */
         for h_seg in self.horizontal_segments() {
             if point.y == h_seg.pos.y {
@@ -497,6 +536,14 @@ mod line_tests {
         }
     }
 
+    #[test]
+    fn extending_empty_line_adds_first_segment() {
+        let mut line = empty_line();
+        line.extend_head(Direction::Down);
+        assert_eq!(line.segments, VecDeque::from(vec![seg!(Direction::Down, 1)]));
+        assert_eq!(line.head(), i32::ivec2(0, 1));
+    }
+
     #[test]
     fn extending_and_shrinking_keeps_length_unchanged() {
         let len = 3;
@@ -526,15 +573,98 @@ mod line_tests {
     }
 
     #[test]
-    fn extending_tail_increases_first_segment_length_and_moves_start_back_by_one() {
-        let len = 5;
-        let mut line = RectilinearLine {
+    fn points_of_empty_line_is_just_start() {
+        assert_eq!(empty_line().points(), vec![i32::ivec2(0, 0)]);
+    }
+
+    #[test]
+    fn points_follow_line_from_start_to_head() {
+        //
+        // ^->
+        // |
+        // o
+        //
+        let line = RectilinearLine {
+            start: i32::ivec2(0, 0),
+            segments: VecDeque::from(vec![seg![Direction::Up, 2], seg![Direction::Right, 1]]),
+        };
+        let points = vec![
+            i32::ivec2(0, 0),
+            i32::ivec2(0, -1),
+            i32::ivec2(0, -2),
+            i32::ivec2(1, -2),
+        ];
+        assert_eq!(line.points(), points);
+        assert_eq!(line.points().len(), line.len());
+    }
+}
+
+#[cfg(test)]
+mod split_tests {
+    use super::*;
+
+    /// A line going right and then up, with the head at (2, -2)
+    ///
+    /// ```text
+    ///   ^
+    ///   |
+    /// o->
+    /// ```
+    fn bent_line() -> RectilinearLine {
+        RectilinearLine {
+            start: i32::ivec2(0, 0),
+            segments: VecDeque::from(vec![seg!(Direction::Right, 2), seg!(Direction::Up, 2)]),
+        }
+    }
+
+    #[test]
+    fn splitting_at_corner_keeps_corner_in_first_part() {
+        let (before, after) = bent_line().split_at(i32::ivec2(2, 0)).unwrap();
+        assert_eq!(before.segments, VecDeque::from(vec![seg!(Direction::Right, 2)]));
+        assert_eq!(after.start, i32::ivec2(2, -1));
+        assert_eq!(after.segments, VecDeque::from(vec![seg!(Direction::Up, 1)]));
+    }
+
+    #[test]
+    fn splitting_at_start_leaves_single_point_before() {
+        let (before, after) = bent_line().split_at(i32::ivec2(0, 0)).unwrap();
+        assert_eq!(before.len(), 1);
+        assert_eq!(after.start, i32::ivec2(1, 0));
+        assert_eq!(after.len(), 4);
+    }
+
+    #[test]
+    fn split_parts_cover_whole_line() {
+        let line = bent_line();
+        let (before, after) = line.split_at(i32::ivec2(1, 0)).unwrap();
+        let mut points = before.points();
+        points.extend(after.points());
+        assert_eq!(points, line.points());
+    }
+
+    #[test]
+    fn splitting_at_head_or_outside_line_gives_nothing() {
+        assert_eq!(bent_line().split_at(i32::ivec2(2, -2)), None);
+        assert_eq!(bent_line().split_at(i32::ivec2(5, 5)), None);
+    }
+
+    #[test]
+    fn splitting_self_overlapping_line_splits_at_first_pass() {
+        // the head has come back round to bite the start
+        let line = RectilinearLine {
             start: i32::ivec2(0, 0),
-            segments: VecDeque::from(vec![seg![Direction::Right, len]]),
+            segments: VecDeque::from(vec![
+                seg!(Direction::Right, 1),
+                seg!(Direction::Down, 1),
+                seg!(Direction::Left, 1),
+                seg!(Direction::Up, 1),
+            ]),
         };
-        line.extend_tail();
-        assert_eq!(line.segments[0].len, len + 1);
-        assert_eq!(line.start, i32::ivec2(-1, 0));
+        let (before, after) = line.split_at(i32::ivec2(0, 0)).unwrap();
+        assert_eq!(before.len(), 1);
+        assert_eq!(after.len(), 4);
+        assert_eq!(after.head(), i32::ivec2(0, 0));
+        assert_eq!(after.is_self_overlapping(), false);
     }
 }
 
@@ -737,6 +867,16 @@ mod collision_tests {
         assert_eq!(line.collides_with_point(point), false);
     }
 
+    #[test]
+    fn point_collides_with_line_without_segments_only_at_start() {
+        let line = RectilinearLine {
+            start: i32::ivec2(3, 2),
+            segments: VecDeque::new(),
+        };
+        assert_eq!(line.collides_with_point(i32::ivec2(3, 2)), true);
+        assert_eq!(line.collides_with_point(i32::ivec2(3, 3)), false);
+    }
+
     #[test]
     fn point_overlapping_inside_horizontal_segment_collides_with_line() {
         //
@@ -0,0 +1,233 @@
+use crate::binary;
+use crate::rectilinear::Direction;
+use crate::storage;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = *b"SNRP";
+pub const FORMAT_VERSION: u8 = 2;
+
+/// Stands in for the snake and direction byte of an event when the autopilot
+/// was toggled
+const AUTOPILOT_BYTE: u8 = 0xff;
+
+/// How a recorded round was set up. The options are stored as the index they
+/// have in the start menu.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ReplaySetup {
+    pub seed: u64, // the round was played with
+    pub difficulty: u8,
+    pub mode: u8,
+    pub level: u8,
+    pub bite_rule: u8,
+    pub players: u8,
+    pub rivals: u8,
+}
+
+/// Something a player did during a round
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Turn { snake: u8, direction: Direction },
+    ToggleAutopilot,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Event {
+    pub frame: u32,
+    pub action: Action,
+}
+
+/// Everything needed to play a round again exactly as it went. The computer
+/// controlled snakes and the items all follow from the seed, so only what the
+/// players did is kept, and only on the frames they did something.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub setup: ReplaySetup,
+    pub frames: u32,        // the round lasted
+    pub score: u32,         // of the first player at the end of the round
+    pub events: Vec<Event>, // in the order they happened
+}
+
+impl Replay {
+    pub fn new(setup: ReplaySetup) -> Self {
+        Replay {
+            setup,
+            frames: 0,
+            score: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Records the direction each snake was steered in on the next frame,
+    /// and whether the autopilot was toggled before it
+    pub fn record(&mut self, actions: &[Option<Direction>], autopilot_toggled: bool) {
+        let frame = self.frames;
+        if autopilot_toggled {
+            self.events.push(Event {
+                frame,
+                action: Action::ToggleAutopilot,
+            });
+        }
+        for (snake, action) in actions.iter().enumerate() {
+            if let Some(direction) = *action {
+                let snake = snake as u8;
+                self.events.push(Event {
+                    frame,
+                    action: Action::Turn { snake, direction },
+                });
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// The direction each of `snakes` snakes was steered in on `frame`, and
+    /// whether the autopilot was toggled before it
+    pub fn actions_at(&self, frame: u32, snakes: usize) -> (Vec<Option<Direction>>, bool) {
+        let start = self.events.partition_point(|event| event.frame < frame);
+        let mut actions = vec![None; snakes];
+        let mut autopilot_toggled = false;
+        for event in self.events[start..]
+            .iter()
+            .take_while(|event| event.frame == frame)
+        {
+            match event.action {
+                Action::Turn { snake, direction } => {
+                    if let Some(action) = actions.get_mut(snake as usize) {
+                        *action = Some(direction);
+                    }
+                }
+                Action::ToggleAutopilot => autopilot_toggled = true,
+            }
+        }
+        (actions, autopilot_toggled)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let setup = &self.setup;
+        let mut bytes = Vec::new();
+        storage::write_versioned(&mut bytes, MAGIC, FORMAT_VERSION);
+        bytes.extend_from_slice(&setup.seed.to_le_bytes());
+        bytes.extend_from_slice(&[
+            setup.difficulty,
+            setup.mode,
+            setup.level,
+            setup.bite_rule,
+            setup.players,
+            setup.rivals,
+        ]);
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        for event in &self.events {
+            bytes.extend_from_slice(&event.frame.to_le_bytes());
+            bytes.push(match event.action {
+                Action::Turn { snake, direction } => {
+                    snake << 3 | binary::encode_direction(Some(direction))
+                }
+                Action::ToggleAutopilot => AUTOPILOT_BYTE,
+            });
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        storage::read_versioned(reader, MAGIC, FORMAT_VERSION, "replays")?;
+        let seed = u64::from_le_bytes(binary::read_bytes(reader)?);
+        let [difficulty, mode, level, bite_rule, players, rivals] = binary::read_bytes(reader)?;
+        let setup = ReplaySetup {
+            seed,
+            difficulty,
+            mode,
+            level,
+            bite_rule,
+            players,
+            rivals,
+        };
+        let frames = u32::from_le_bytes(binary::read_bytes(reader)?);
+        let score = u32::from_le_bytes(binary::read_bytes(reader)?);
+        let num_events = u32::from_le_bytes(binary::read_bytes(reader)?);
+        let mut events = Vec::new();
+        for _ in 0..num_events {
+            let frame = u32::from_le_bytes(binary::read_bytes(reader)?);
+            let byte = binary::read_bytes::<_, 1>(reader)?[0];
+            let action = if byte == AUTOPILOT_BYTE {
+                Action::ToggleAutopilot
+            } else {
+                let direction = binary::decode_direction(byte & 0b111)?
+                    .ok_or_else(|| binary::invalid_data("turn without direction".to_string()))?;
+                Action::Turn {
+                    snake: byte >> 3,
+                    direction,
+                }
+            };
+            if frame >= frames || events.last().is_some_and(|last: &Event| last.frame > frame) {
+                return Err(binary::invalid_data(format!(
+                    "event out of order at frame {}",
+                    frame
+                )));
+            }
+            events.push(Event { frame, action });
+        }
+        Ok(Replay {
+            setup,
+            frames,
+            score,
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn recorded_replay() -> Replay {
+        let mut replay = Replay::new(ReplaySetup {
+            seed: 0x0123_4567_89ab_cdef,
+            difficulty: 1,
+            mode: 2,
+            level: 3,
+            bite_rule: 1,
+            players: 1,
+            rivals: 2,
+        });
+        replay.record(&[None, None], false);
+        replay.record(&[Some(Direction::Left), Some(Direction::Up)], true);
+        replay.record(&[None, None], false);
+        replay.record(&[None, Some(Direction::Down)], false);
+        replay.score = 1234;
+        replay
+    }
+
+    #[test]
+    fn only_frames_with_actions_are_kept() {
+        let replay = recorded_replay();
+        assert_eq!(replay.frames, 4);
+        assert_eq!(replay.events.len(), 4);
+    }
+
+    #[test]
+    fn actions_are_played_back_on_the_frame_they_happened() {
+        let replay = recorded_replay();
+        assert_eq!(replay.actions_at(0, 2), (vec![None, None], false));
+        assert_eq!(
+            replay.actions_at(1, 2),
+            (vec![Some(Direction::Left), Some(Direction::Up)], true)
+        );
+        assert_eq!(replay.actions_at(2, 2), (vec![None, None], false));
+        assert_eq!(
+            replay.actions_at(3, 2),
+            (vec![None, Some(Direction::Down)], false)
+        );
+    }
+
+    #[test]
+    fn replays_survive_round_trip() {
+        let replay = recorded_replay();
+        let mut bytes = Vec::new();
+        replay.write_to(&mut bytes).unwrap();
+        assert_eq!(Replay::read_from(&mut Cursor::new(bytes)).unwrap(), replay);
+    }
+}
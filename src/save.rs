@@ -0,0 +1,125 @@
+use crate::binary;
+use crate::replay::Replay;
+use crate::storage;
+use crate::versus::MatchScore;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = *b"SNSV";
+pub const FORMAT_VERSION: u8 = 3;
+
+/// A round left unfinished, to be picked up again later.
+///
+/// Rather than every part of the round, the save holds the replay of the round
+/// so far. Playing it back brings the snakes, items, walls, scores and random
+/// number generator back exactly as they were when the round was left. The
+/// hash of the round as it was left is kept as well, since a version of the
+/// game that plays differently would play the replay back to another round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedRound {
+    pub game_seed: u64,  // the game the round is part of was started with
+    pub state_hash: u64, // of the round where it was left
+    pub ghost: bool,     // whether the round was raced against a ghost
+    pub match_score: Option<MatchScore>,
+    pub replay: Replay, // of the round up to where it was left
+}
+
+impl SavedRound {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        storage::write_versioned(&mut bytes, MAGIC, FORMAT_VERSION);
+        bytes.extend_from_slice(&self.game_seed.to_le_bytes());
+        bytes.extend_from_slice(&self.state_hash.to_le_bytes());
+        bytes.push(self.ghost as u8);
+        match &self.match_score {
+            Some(score) => {
+                bytes.push(score.wins.len() as u8);
+                bytes.push(score.wins_needed as u8);
+                bytes.extend(score.wins.iter().map(|&wins| wins as u8));
+            }
+            None => bytes.push(0),
+        }
+        writer.write_all(&bytes)?;
+        self.replay.write_to(writer)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        storage::read_versioned(reader, MAGIC, FORMAT_VERSION, "saved rounds")?;
+        let game_seed = u64::from_le_bytes(binary::read_bytes(reader)?);
+        let state_hash = u64::from_le_bytes(binary::read_bytes(reader)?);
+        let [ghost, players] = binary::read_bytes(reader)?;
+        let match_score = if players > 0 {
+            let wins_needed = binary::read_bytes::<_, 1>(reader)?[0] as usize;
+            let mut score = MatchScore::new(players as usize, wins_needed);
+            for wins in &mut score.wins {
+                *wins = binary::read_bytes::<_, 1>(reader)?[0] as usize;
+            }
+            Some(score)
+        } else {
+            None
+        };
+        Ok(SavedRound {
+            game_seed,
+            state_hash,
+            ghost: ghost != 0,
+            match_score,
+            replay: Replay::read_from(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectilinear::Direction;
+    use crate::replay::ReplaySetup;
+    use std::io::Cursor;
+
+    fn saved_round() -> SavedRound {
+        let mut replay = Replay::new(ReplaySetup {
+            seed: 42,
+            difficulty: 2,
+            mode: 0,
+            level: 1,
+            bite_rule: 0,
+            players: 2,
+            rivals: 1,
+        });
+        replay.record(&[Some(Direction::Up), None, None], false);
+        replay.record(&[None, Some(Direction::Left), None], false);
+        SavedRound {
+            game_seed: 0xfeed_f00d,
+            state_hash: 0x0123_4567_89ab_cdef,
+            ghost: false,
+            match_score: Some(MatchScore {
+                wins: vec![2, 1],
+                wins_needed: 3,
+            }),
+            replay,
+        }
+    }
+
+    #[test]
+    fn saves_survive_round_trip() {
+        let saved = saved_round();
+        let mut bytes = Vec::new();
+        saved.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            SavedRound::read_from(&mut Cursor::new(bytes)).unwrap(),
+            saved
+        );
+
+        let single_player = SavedRound {
+            ghost: true,
+            match_score: None,
+            ..saved_round()
+        };
+        let mut bytes = Vec::new();
+        single_player.write_to(&mut bytes).unwrap();
+        assert_eq!(
+            SavedRound::read_from(&mut Cursor::new(bytes)).unwrap(),
+            single_player
+        );
+    }
+}
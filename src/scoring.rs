@@ -0,0 +1,285 @@
+use glam::i32;
+use glam::IVec2;
+
+/// Number of frames after scoring during which the next score builds the combo
+const COMBO_WINDOW: usize = 3 * 60;
+
+/// The combo multiplier never goes above this
+const MAX_MULTIPLIER: usize = 8;
+
+/// Every this many cells of snake length gives another 10% length bonus
+const LENGTH_BONUS_STEP: usize = 5;
+
+/// Number of frames a score pop-up is shown for
+const POPUP_DURATION: usize = 45;
+
+/// Number of frames between each step a score pop-up floats upwards
+const POPUP_RISE_PERIOD: usize = 15;
+
+/// Where all the points of a round came from
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct ScoreBreakdown {
+    pub base: usize,
+    pub length_bonus: usize,
+    pub combo_bonus: usize,
+    pub difficulty_bonus: usize,
+    pub penalty: usize, // taken off the rest, but never below zero in total
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> usize {
+        self.base + self.length_bonus + self.combo_bonus + self.difficulty_bonus - self.penalty
+    }
+
+    fn add(&mut self, other: &ScoreBreakdown) {
+        self.base += other.base;
+        self.length_bonus += other.length_bonus;
+        self.combo_bonus += other.combo_bonus;
+        self.difficulty_bonus += other.difficulty_bonus;
+        self.penalty += other.penalty;
+    }
+}
+
+/// A short-lived text showing how many points were just scored, floating
+/// upwards from where they were scored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScorePopup {
+    pub text: String,
+    pub origin: IVec2,
+    pub frames: usize,
+}
+
+impl ScorePopup {
+    pub fn pos(&self) -> IVec2 {
+        self.origin - i32::ivec2(0, (self.frames / POPUP_RISE_PERIOD) as i32)
+    }
+}
+
+/// Keeps track of the score of a round, and the combo multiplier that builds
+/// up when scoring points in quick succession.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scoring {
+    pub breakdown: ScoreBreakdown,
+    pub multiplier: usize,
+    pub popups: Vec<ScorePopup>,
+    combo_frames_left: usize,
+    difficulty_percent: usize,
+}
+
+impl Scoring {
+    /// `difficulty_percent` is how much all points are scaled by, in percent
+    pub fn new(difficulty_percent: usize) -> Self {
+        Scoring {
+            breakdown: ScoreBreakdown::default(),
+            multiplier: 1,
+            popups: Vec::new(),
+            combo_frames_left: 0,
+            difficulty_percent,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.breakdown.total()
+    }
+
+    /// Counts down the combo window and the pop-up lifetimes by one frame
+    pub fn tick(&mut self) {
+        self.combo_frames_left = self.combo_frames_left.saturating_sub(1);
+        if self.combo_frames_left == 0 {
+            self.multiplier = 1;
+        }
+
+        for popup in &mut self.popups {
+            popup.frames += 1;
+        }
+        self.popups.retain(|popup| popup.frames < POPUP_DURATION);
+    }
+
+    /// Awards `points` scaled by the snake length, combo and difficulty, and
+    /// shows a pop-up at `pos`. Returns the number of points awarded.
+    pub fn award(&mut self, points: usize, snake_len: usize, pos: IVec2) -> usize {
+        if points == 0 {
+            return 0;
+        }
+
+        // scoring within the combo window builds the combo
+        if self.combo_frames_left > 0 {
+            self.multiplier = usize::min(self.multiplier + 1, MAX_MULTIPLIER);
+        }
+        self.combo_frames_left = COMBO_WINDOW;
+
+        let score = self.score_points(points, snake_len);
+        self.breakdown.add(&score);
+
+        let text = if self.multiplier > 1 {
+            format!("+{} x{}", score.total(), self.multiplier)
+        } else {
+            format!("+{}", score.total())
+        };
+        self.popups.push(ScorePopup {
+            text,
+            origin: pos,
+            frames: 0,
+        });
+
+        score.total()
+    }
+
+    /// Takes `points` off the score, as far as there are points to take, and
+    /// shows a pop-up at `pos`. The combo is broken as well. Returns the
+    /// number of points taken off.
+    pub fn deduct(&mut self, points: usize, pos: IVec2) -> usize {
+        self.break_combo();
+        let points = usize::min(points, self.total());
+        if points == 0 {
+            return 0;
+        }
+        self.breakdown.penalty += points;
+        self.popups.push(ScorePopup {
+            text: format!("-{}", points),
+            origin: pos,
+            frames: 0,
+        });
+        points
+    }
+
+    /// Resets the combo multiplier
+    pub fn break_combo(&mut self) {
+        self.multiplier = 1;
+        self.combo_frames_left = 0;
+    }
+
+    fn score_points(&self, points: usize, snake_len: usize) -> ScoreBreakdown {
+        let base = points;
+        let length_bonus = base * (snake_len / LENGTH_BONUS_STEP) / 10;
+        let combo_bonus = (base + length_bonus) * (self.multiplier - 1);
+        let subtotal = base + length_bonus + combo_bonus;
+        let difficulty_bonus = subtotal * self.difficulty_percent / 100 - subtotal;
+        ScoreBreakdown {
+            base,
+            length_bonus,
+            combo_bonus,
+            difficulty_bonus,
+            penalty: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin() -> IVec2 {
+        i32::ivec2(0, 0)
+    }
+
+    #[test]
+    fn single_award_without_bonuses_gives_base_points() {
+        let mut scoring = Scoring::new(100);
+        assert_eq!(scoring.award(100, 4, origin()), 100);
+        assert_eq!(scoring.total(), 100);
+        assert_eq!(scoring.multiplier, 1);
+    }
+
+    #[test]
+    fn awards_within_combo_window_increase_multiplier() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        scoring.tick();
+        assert_eq!(scoring.award(100, 4, origin()), 200);
+        scoring.tick();
+        assert_eq!(scoring.award(100, 4, origin()), 300);
+        assert_eq!(scoring.multiplier, 3);
+        assert_eq!(scoring.breakdown.combo_bonus, 300);
+    }
+
+    #[test]
+    fn combo_is_lost_after_window_runs_out() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        scoring.award(100, 4, origin());
+        for _ in 0..COMBO_WINDOW {
+            scoring.tick();
+        }
+        assert_eq!(scoring.multiplier, 1);
+        assert_eq!(scoring.award(100, 4, origin()), 100);
+    }
+
+    #[test]
+    fn multiplier_is_capped() {
+        let mut scoring = Scoring::new(100);
+        for _ in 0..2 * MAX_MULTIPLIER {
+            scoring.award(100, 4, origin());
+        }
+        assert_eq!(scoring.multiplier, MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn longer_snakes_earn_length_bonus() {
+        let mut scoring = Scoring::new(100);
+        assert_eq!(scoring.award(100, 2 * LENGTH_BONUS_STEP, origin()), 120);
+        assert_eq!(scoring.breakdown.length_bonus, 20);
+    }
+
+    #[test]
+    fn difficulty_scales_points() {
+        let mut scoring = Scoring::new(150);
+        assert_eq!(scoring.award(100, 4, origin()), 150);
+        assert_eq!(scoring.breakdown.difficulty_bonus, 50);
+    }
+
+    #[test]
+    fn breakdown_adds_up_to_total() {
+        let mut scoring = Scoring::new(200);
+        let mut awarded = 0;
+        for len in 4..20 {
+            awarded += scoring.award(100, len, origin());
+        }
+        assert_eq!(scoring.total(), awarded);
+    }
+
+    #[test]
+    fn popup_shows_multiplier_and_floats_upwards() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        scoring.award(100, 4, origin());
+        assert_eq!(scoring.popups[1].text, "+200 x2");
+
+        for _ in 0..POPUP_RISE_PERIOD {
+            scoring.tick();
+        }
+        assert_eq!(scoring.popups[1].pos(), i32::ivec2(0, -1));
+    }
+
+    #[test]
+    fn popups_disappear_after_a_while() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        for _ in 0..POPUP_DURATION {
+            scoring.tick();
+        }
+        assert!(scoring.popups.is_empty());
+    }
+
+    #[test]
+    fn deductions_never_take_score_below_zero() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        assert_eq!(scoring.deduct(30, origin()), 30);
+        assert_eq!(scoring.total(), 70);
+        assert_eq!(scoring.popups[1].text, "-30");
+        assert_eq!(scoring.deduct(100, origin()), 70);
+        assert_eq!(scoring.total(), 0);
+        assert_eq!(scoring.breakdown.penalty, 100);
+    }
+
+    #[test]
+    fn breaking_combo_resets_multiplier() {
+        let mut scoring = Scoring::new(100);
+        scoring.award(100, 4, origin());
+        scoring.award(100, 4, origin());
+        scoring.break_combo();
+        assert_eq!(scoring.multiplier, 1);
+        assert_eq!(scoring.award(100, 4, origin()), 100);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::rectilinear::Direction;
+use crate::rectilinear::RectilinearLine;
+use glam::IVec2;
+use std::collections::VecDeque;
+
+/// The body of a snake, made up of one or more runs of connected cells.
+///
+/// A snake going through a portal comes out somewhere else entirely, so for as
+/// long as it's passing through, its body is split in two. Every time the head
+/// jumps a new run is started, and runs are dropped from the tail end once the
+/// snake has passed through them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnakeBody {
+    pub runs: VecDeque<RectilinearLine>, // from the tail to the head
+}
+
+impl SnakeBody {
+    pub fn new(line: RectilinearLine) -> Self {
+        SnakeBody {
+            runs: VecDeque::from(vec![line]),
+        }
+    }
+
+    fn head_run(&self) -> &RectilinearLine {
+        self.runs.back().expect("snake body has at least one run")
+    }
+
+    fn tail_run(&self) -> &RectilinearLine {
+        self.runs.front().expect("snake body has at least one run")
+    }
+
+    /// Total number of cells covered by all the runs
+    pub fn len(&self) -> usize {
+        self.runs.iter().map(|run| run.len()).sum()
+    }
+
+    /// The direction of the head run, which is `None` right after the head
+    /// has jumped, until it has moved out of the cell it jumped to.
+    pub fn dir(&self) -> Option<Direction> {
+        self.head_run().dir()
+    }
+
+    pub fn head(&self) -> IVec2 {
+        self.head_run().head()
+    }
+
+    pub fn tail(&self) -> IVec2 {
+        self.tail_run().start
+    }
+
+    /// Every point covered by the body, from the tail to the head
+    pub fn points(&self) -> Vec<IVec2> {
+        self.runs.iter().flat_map(|run| run.points()).collect()
+    }
+
+    /// Removes the last cell of the tail, dropping the tail run once there's
+    /// nothing left of it
+    pub fn shrink_tail(&mut self) {
+        let tail_is_point = self.tail_run().segments.is_empty();
+        if tail_is_point && self.runs.len() > 1 {
+            self.runs.pop_front();
+        } else if !tail_is_point {
+            self.runs[0].shrink_tail();
+        }
+    }
+
+    pub fn extend_head(&mut self, dir: Direction) {
+        let last_index = self.runs.len() - 1;
+        self.runs[last_index].extend_head(dir);
+    }
+
+    pub fn move_forward(&mut self, dir: Direction) {
+        if self.runs.len() == 1 {
+            self.runs[0].move_forward(dir);
+        } else {
+            self.shrink_tail();
+            self.extend_head(dir);
+        }
+    }
+
+    /// Moves the head to `to` without covering the cells in between, starting
+    /// a new run there
+    pub fn jump_head(&mut self, to: IVec2) {
+        self.runs.push_back(RectilinearLine {
+            start: to,
+            segments: VecDeque::new(),
+        });
+    }
+
+    /// Cuts the body in two right after the first cell at `point`, counting
+    /// from the tail. The body keeps the part ahead of the cut, and the part
+    /// behind it is returned as the runs it's made up of. Returns `None` if
+    /// the body doesn't cover `point`, or if there would be nothing left.
+    pub fn cut_at(&mut self, point: IVec2) -> Option<Vec<RectilinearLine>> {
+        let index = self
+            .runs
+            .iter()
+            .position(|run| run.collides_with_point(point))?;
+        let (behind, ahead) = match self.runs[index].split_at(point) {
+            Some((behind, ahead)) => (behind, Some(ahead)),
+            // the point is the end of its run, so the whole run is cut off
+            None if index + 1 < self.runs.len() => (self.runs[index].clone(), None),
+            None => return None,
+        };
+
+        let mut cut_off: Vec<RectilinearLine> = self.runs.drain(..=index).collect();
+        cut_off.pop();
+        cut_off.push(behind);
+        if let Some(ahead) = ahead {
+            self.runs.push_front(ahead);
+        }
+        Some(cut_off)
+    }
+
+    /// Checks if any run overlaps itself or any of the other runs
+    pub fn is_self_overlapping(&self) -> bool {
+        self.runs.iter().enumerate().any(|(index, run)| {
+            run.is_self_overlapping()
+                || self.runs.iter().skip(index + 1).any(|other| {
+                    other
+                        .points()
+                        .iter()
+                        .any(|&point| run.collides_with_point(point))
+                })
+        })
+    }
+
+    pub fn collides_with_point(&self, point: IVec2) -> bool {
+        self.runs.iter().any(|run| run.collides_with_point(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectilinear::ChainedLineSegment;
+    use glam::i32;
+
+    /// A body going right from the origin, which has jumped to (0, 5) and
+    /// gone one step down from there
+    fn split_body() -> SnakeBody {
+        let mut body = SnakeBody::new(RectilinearLine {
+            start: i32::ivec2(0, 0),
+            segments: VecDeque::from(vec![seg!(Direction::Right, 2)]),
+        });
+        body.jump_head(i32::ivec2(0, 5));
+        body.extend_head(Direction::Down);
+        body
+    }
+
+    #[test]
+    fn jumping_starts_a_new_run_at_the_head() {
+        let body = split_body();
+        assert_eq!(body.runs.len(), 2);
+        assert_eq!(body.head(), i32::ivec2(0, 6));
+        assert_eq!(body.tail(), i32::ivec2(0, 0));
+        assert_eq!(body.len(), 5);
+        assert_eq!(body.dir(), Some(Direction::Down));
+    }
+
+    #[test]
+    fn points_cover_every_run_from_tail_to_head() {
+        let points = vec![
+            i32::ivec2(0, 0),
+            i32::ivec2(1, 0),
+            i32::ivec2(2, 0),
+            i32::ivec2(0, 5),
+            i32::ivec2(0, 6),
+        ];
+        assert_eq!(split_body().points(), points);
+    }
+
+    #[test]
+    fn tail_run_is_dropped_once_passed_through() {
+        let mut body = split_body();
+        for _ in 0..3 {
+            body.move_forward(Direction::Down);
+        }
+        assert_eq!(body.runs.len(), 1);
+        assert_eq!(body.len(), 5);
+        assert_eq!(body.points().first(), Some(&i32::ivec2(0, 5)));
+    }
+
+    #[test]
+    fn points_in_any_run_collide() {
+        let body = split_body();
+        assert!(body.collides_with_point(i32::ivec2(1, 0)));
+        assert!(body.collides_with_point(i32::ivec2(0, 5)));
+        assert!(!body.collides_with_point(i32::ivec2(0, 3)));
+    }
+
+    #[test]
+    fn cutting_keeps_the_part_ahead_of_the_cut() {
+        let mut body = split_body();
+        let cut_off = body.cut_at(i32::ivec2(1, 0)).unwrap();
+        assert_eq!(
+            cut_off
+                .iter()
+                .flat_map(|run| run.points())
+                .collect::<Vec<IVec2>>(),
+            vec![i32::ivec2(0, 0), i32::ivec2(1, 0)]
+        );
+        assert_eq!(body.runs.len(), 2);
+        assert_eq!(body.tail(), i32::ivec2(2, 0));
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn cutting_at_end_of_run_cuts_off_whole_run() {
+        let mut body = split_body();
+        let cut_off = body.cut_at(i32::ivec2(2, 0)).unwrap();
+        assert_eq!(cut_off.len(), 1);
+        assert_eq!(body.runs.len(), 1);
+        assert_eq!(body.tail(), i32::ivec2(0, 5));
+    }
+
+    #[test]
+    fn cutting_at_head_or_outside_body_does_nothing() {
+        let mut body = split_body();
+        assert_eq!(body.cut_at(i32::ivec2(0, 6)), None);
+        assert_eq!(body.cut_at(i32::ivec2(9, 9)), None);
+        assert_eq!(body, split_body());
+    }
+
+    #[test]
+    fn runs_overlapping_each_other_are_self_overlapping() {
+        let mut body = split_body();
+        assert!(!body.is_self_overlapping());
+        body.jump_head(i32::ivec2(1, 0));
+        assert!(body.is_self_overlapping());
+    }
+}
@@ -0,0 +1,135 @@
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Spectators that can't keep up with the game for this long get dropped, so
+/// that they don't slow the game down for everyone else
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Sends everything written to it to every spectator that has connected on a
+/// port. Spectators only ever receive, they have no say in the game.
+#[derive(Debug)]
+pub struct Broadcast {
+    listener: TcpListener,
+    spectators: Vec<TcpStream>,
+}
+
+impl Broadcast {
+    /// Starts listening for spectators on `port`. Only spectators on this
+    /// computer can connect, since the stream isn't for the whole network.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcast {
+            listener,
+            spectators: Vec::new(),
+        })
+    }
+
+    /// Lets in the spectators that are waiting to connect. Returns whether
+    /// anyone new joined, since they will need to be sent the whole screen.
+    pub fn accept_spectators(&mut self) -> bool {
+        let mut anyone_joined = false;
+        while let Ok((stream, _)) = self.listener.accept() {
+            let ready = stream
+                .set_nonblocking(false)
+                .and_then(|_| stream.set_nodelay(true))
+                .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)));
+            if ready.is_ok() {
+                self.spectators.push(stream);
+                anyone_joined = true;
+            }
+        }
+        anyone_joined
+    }
+}
+
+impl Write for Broadcast {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.spectators
+            .retain(|spectator| (&*spectator).write_all(bytes).is_ok());
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.spectators
+            .retain(|spectator| (&*spectator).flush().is_ok());
+        Ok(())
+    }
+}
+
+/// Shows the game broadcast at `address` on the terminal until the broadcast
+/// ends. The broadcast is drawn with ANSI escape codes, so it can just be
+/// passed on to the terminal as it is.
+pub fn watch(address: &str) -> io::Result<()> {
+    let mut stream = TcpStream::connect(address)?;
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut buffer = [0; 4096];
+    loop {
+        let count = stream.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        stdout.write_all(&buffer[..count])?;
+        stdout.flush()?;
+    }
+    // show the cursor again, and leave the last frame on screen
+    write!(stdout, "\x1b[0m\x1b[?25h\r\nThe broadcast has ended\r\n")?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broadcast_with_spectators(count: usize) -> (Broadcast, Vec<TcpStream>) {
+        let mut broadcast = Broadcast::bind(0).unwrap();
+        let address = broadcast.listener.local_addr().unwrap();
+        let spectators: Vec<TcpStream> = (0..count)
+            .map(|_| TcpStream::connect(("127.0.0.1", address.port())).unwrap())
+            .collect();
+        while broadcast.spectators.len() < count {
+            broadcast.accept_spectators();
+        }
+        (broadcast, spectators)
+    }
+
+    #[test]
+    fn every_spectator_receives_the_broadcast() {
+        let (mut broadcast, spectators) = broadcast_with_spectators(2);
+        broadcast.write_all(b"frame").unwrap();
+        broadcast.flush().unwrap();
+
+        for mut spectator in spectators {
+            let mut received = [0; 5];
+            spectator.read_exact(&mut received).unwrap();
+            assert_eq!(&received, b"frame");
+        }
+    }
+
+    #[test]
+    fn nobody_joining_is_reported() {
+        let (mut broadcast, _spectators) = broadcast_with_spectators(1);
+        assert!(!broadcast.accept_spectators());
+    }
+
+    #[test]
+    fn spectators_that_leave_are_dropped() {
+        let (mut broadcast, mut spectators) = broadcast_with_spectators(2);
+        drop(spectators.pop());
+
+        // it can take a few writes before a closed connection is noticed
+        for _ in 0..100 {
+            broadcast.write_all(&[0; 1024]).unwrap();
+            if broadcast.spectators.len() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(broadcast.spectators.len(), 1);
+    }
+}
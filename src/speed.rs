@@ -0,0 +1,81 @@
+/// Describes how the movement period of the snake shrinks as the snake grows.
+///
+/// Periods are measured in frames per movement step and are fractional, so
+/// that the speed can ramp up in smaller steps than one whole frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpeedCurve {
+    pub initial_period: f32,
+    pub min_period: f32,
+    pub period_step: f32,
+    pub growth_per_level: usize,
+}
+
+impl SpeedCurve {
+    /// The speed level reached after having grown `growth` cells, starting
+    /// from level 0 and capped at the level where `min_period` is reached.
+    pub fn level(&self, growth: usize) -> usize {
+        usize::min(growth / self.growth_per_level, self.max_level())
+    }
+
+    /// The level at which the movement period bottoms out at `min_period`
+    pub fn max_level(&self) -> usize {
+        ((self.initial_period - self.min_period) / self.period_step).ceil() as usize
+    }
+
+    /// The movement period in frames at the given speed `level`
+    pub fn period(&self, level: usize) -> f32 {
+        f32::max(
+            self.initial_period - level as f32 * self.period_step,
+            self.min_period,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_curve() -> SpeedCurve {
+        SpeedCurve {
+            initial_period: 6.0,
+            min_period: 3.0,
+            period_step: 0.5,
+            growth_per_level: 2,
+        }
+    }
+
+    #[test]
+    fn level_is_zero_before_growing() {
+        assert_eq!(test_curve().level(0), 0);
+        assert_eq!(test_curve().level(1), 0);
+    }
+
+    #[test]
+    fn level_increases_every_growth_per_level_cells() {
+        let curve = test_curve();
+        assert_eq!(curve.level(2), 1);
+        assert_eq!(curve.level(5), 2);
+    }
+
+    #[test]
+    fn period_shrinks_by_step_for_each_level() {
+        let curve = test_curve();
+        assert_eq!(curve.period(0), 6.0);
+        assert_eq!(curve.period(1), 5.5);
+        assert_eq!(curve.period(4), 4.0);
+    }
+
+    #[test]
+    fn period_never_goes_below_minimum() {
+        let curve = test_curve();
+        assert_eq!(curve.period(100), curve.min_period);
+    }
+
+    #[test]
+    fn level_is_capped_where_minimum_period_is_reached() {
+        let curve = test_curve();
+        assert_eq!(curve.max_level(), 6);
+        assert_eq!(curve.level(1000), 6);
+        assert_eq!(curve.period(curve.max_level()), curve.min_period);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::binary;
+use crate::storage;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+const MAGIC: [u8; 4] = *b"SNST";
+pub const FORMAT_VERSION: u8 = 1;
+
+/// What a snake died of
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DeathCause {
+    Wall,   // including the obstacles of a level and the closing walls
+    Itself, // its own body or trail
+    Rival,  // another snake or its trail
+}
+
+/// What a snake got up to over a round, kept for the lifetime statistics
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct RoundTally {
+    pub apples: usize,
+    pub longest: usize, // the snake has been, in cells
+    pub wall_deaths: usize,
+    pub self_deaths: usize,
+    pub rival_deaths: usize,
+}
+
+impl RoundTally {
+    pub fn new(length: usize) -> Self {
+        RoundTally {
+            longest: length,
+            ..RoundTally::default()
+        }
+    }
+
+    pub fn record_death(&mut self, cause: DeathCause) {
+        match cause {
+            DeathCause::Wall => self.wall_deaths += 1,
+            DeathCause::Itself => self.self_deaths += 1,
+            DeathCause::Rival => self.rival_deaths += 1,
+        }
+    }
+}
+
+/// Everything the players on this computer have done, over every game played
+/// on it. Each round a player plays counts as a game of its own.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Stats {
+    pub games: u64,
+    pub total_score: u64,
+    pub apples: u64,
+    pub longest_snake: u64, // in cells
+    pub frames_played: u64,
+    pub wall_deaths: u64,
+    pub self_deaths: u64,
+    pub rival_deaths: u64,
+}
+
+impl Stats {
+    /// Adds a round that lasted `frames`, with the score and tally of each
+    /// player that played it
+    pub fn record_round(&mut self, frames: u32, players: &[(usize, RoundTally)]) {
+        self.frames_played += frames as u64;
+        for (score, tally) in players {
+            self.games += 1;
+            self.total_score += *score as u64;
+            self.apples += tally.apples as u64;
+            self.longest_snake = u64::max(self.longest_snake, tally.longest as u64);
+            self.wall_deaths += tally.wall_deaths as u64;
+            self.self_deaths += tally.self_deaths as u64;
+            self.rival_deaths += tally.rival_deaths as u64;
+        }
+    }
+
+    pub fn average_score(&self) -> u64 {
+        self.total_score.checked_div(self.games).unwrap_or(0)
+    }
+
+    fn fields(&self) -> [u64; 8] {
+        [
+            self.games,
+            self.total_score,
+            self.apples,
+            self.longest_snake,
+            self.frames_played,
+            self.wall_deaths,
+            self.self_deaths,
+            self.rival_deaths,
+        ]
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        storage::write_versioned(&mut bytes, MAGIC, FORMAT_VERSION);
+        for field in self.fields().iter() {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        storage::read_versioned(reader, MAGIC, FORMAT_VERSION, "statistics")?;
+        // the fields are read in the order they're written in
+        let mut field = || binary::read_bytes(reader).map(u64::from_le_bytes);
+        Ok(Stats {
+            games: field()?,
+            total_score: field()?,
+            apples: field()?,
+            longest_snake: field()?,
+            frames_played: field()?,
+            wall_deaths: field()?,
+            self_deaths: field()?,
+            rival_deaths: field()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn played_stats() -> Stats {
+        let mut first = RoundTally::new(4);
+        first.apples = 3;
+        first.longest = 7;
+        first.record_death(DeathCause::Wall);
+        let mut second = RoundTally::new(4);
+        second.record_death(DeathCause::Itself);
+        second.record_death(DeathCause::Wall);
+
+        let mut stats = Stats::default();
+        stats.record_round(600, &[(300, first)]);
+        stats.record_round(120, &[(100, second), (0, RoundTally::new(4))]);
+        stats
+    }
+
+    #[test]
+    fn rounds_add_up() {
+        let stats = played_stats();
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.total_score, 400);
+        assert_eq!(stats.average_score(), 133);
+        assert_eq!(stats.apples, 3);
+        assert_eq!(stats.longest_snake, 7);
+        assert_eq!(stats.frames_played, 720);
+        assert_eq!(
+            (stats.wall_deaths, stats.self_deaths, stats.rival_deaths),
+            (2, 1, 0)
+        );
+        assert_eq!(Stats::default().average_score(), 0);
+    }
+
+    #[test]
+    fn stats_survive_round_trip() {
+        let stats = played_stats();
+        let mut bytes = Vec::new();
+        stats.write_to(&mut bytes).unwrap();
+        assert_eq!(Stats::read_from(&mut Cursor::new(bytes)).unwrap(), stats);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::binary;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+/// Name of the folder the game keeps its files in, inside the data folder of
+/// the user
+const GAME_DIR: &str = "curses_snake";
+
+/// Held by whoever is reading a file in the data folder to save it again
+/// changed, since every telnet session of `--serve` plays on a thread of its
+/// own
+static DATA_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Number of files written so far, to give every temporary file a name of its
+/// own
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Where the game keeps files between runs, which is in `%APPDATA%` on Windows
+/// and in `$XDG_DATA_HOME` or `~/.local/share` elsewhere. Returns `None` if
+/// the user has no such folder.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+    };
+    base.map(|base| base.join(GAME_DIR))
+}
+
+/// Keeps the other threads of the game from changing the files in the data
+/// folder until the returned guard is dropped, so that a file can be read,
+/// changed and saved again without losing what another thread saved to it in
+/// between
+pub fn lock_data_dir() -> MutexGuard<'static, ()> {
+    // a thread that panicked while saving leaves nothing to clean up
+    DATA_DIR_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Writes `bytes` to the file at `path`, creating any missing folders on the
+/// way. The bytes go to a file next to it first, which is then renamed over
+/// the old file, so that a crash halfway through never leaves a broken file.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    let write = WRITES.fetch_add(1, Ordering::Relaxed);
+    temp_name.push(format!(".{}.{}.tmp", process::id(), write));
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+/// Opens the file called `name` in the data folder for reading, if there is
+/// one
+pub fn open_in_data_dir(name: &str) -> Option<io::BufReader<fs::File>> {
+    let file = fs::File::open(data_dir()?.join(name)).ok()?;
+    Some(io::BufReader::new(file))
+}
+
+/// Saves the bytes that `write` writes to the file called `name` in the data
+/// folder, over what was in it before. Not being able to save is no reason to
+/// stop playing, so the file is left as it was if anything goes wrong.
+pub fn save_to_data_dir(name: &str, write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) {
+    if let Some(dir) = data_dir() {
+        let mut bytes = Vec::new();
+        let _ = write(&mut bytes).and_then(|()| write_atomically(&dir.join(name), &bytes));
+    }
+}
+
+/// Starts a file of the game with the `magic` bytes of its kind of file and
+/// the `version` of its format. The version is bumped whenever the format
+/// changes, so that older files are refused instead of being read as
+/// something else.
+pub fn write_versioned(bytes: &mut Vec<u8>, magic: [u8; 4], version: u8) {
+    bytes.extend_from_slice(&magic);
+    bytes.push(version);
+}
+
+/// Reads the start of a file written with `write_versioned`, failing unless
+/// it's a file of `what` with the `magic` bytes and `version` given
+pub fn read_versioned<R: Read>(
+    reader: &mut R,
+    magic: [u8; 4],
+    version: u8,
+    what: &str,
+) -> io::Result<()> {
+    if binary::read_bytes::<_, 4>(reader)? != magic {
+        return Err(binary::invalid_data(format!("not a file of {}", what)));
+    }
+    let [file_version] = binary::read_bytes(reader)?;
+    if file_version != version {
+        return Err(binary::invalid_data(format!(
+            "{} are of version {}, but this game reads version {}",
+            what, file_version, version
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn writing_replaces_the_whole_file() {
+        let dir = env::temp_dir().join(format!("curses_snake_test_{}", std::process::id()));
+        let path = dir.join("nested").join("file.bin");
+        write_atomically(&path, b"first version").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_from_many_threads_all_succeed() {
+        let dir = env::temp_dir().join(format!("curses_snake_threads_{}", process::id()));
+        let path = dir.join("file.bin");
+        let writers: Vec<_> = (0..8)
+            .map(|thread| {
+                let path = path.clone();
+                std::thread::spawn(move || write_atomically(&path, &[thread; 64]))
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+        assert_eq!(fs::read(&path).unwrap().len(), 64);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn other_versions_and_files_are_refused() {
+        let mut bytes = Vec::new();
+        write_versioned(&mut bytes, *b"SNXX", 3);
+        bytes.push(42);
+        let mut reader = Cursor::new(&bytes);
+        assert!(read_versioned(&mut reader, *b"SNXX", 3, "tests").is_ok());
+        assert_eq!(binary::read_bytes(&mut reader).unwrap(), [42]);
+
+        assert!(read_versioned(&mut Cursor::new(&bytes), *b"SNXX", 4, "tests").is_err());
+        assert!(read_versioned(&mut Cursor::new(&bytes), *b"SNYY", 3, "tests").is_err());
+        assert!(read_versioned(&mut Cursor::new(b"SN"), *b"SNXX", 3, "tests").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use glam::IVec2;
+
+/// Frames between each time the arena closes in
+pub const CLOSE_PERIOD_FRAMES: usize = 20 * 60;
+
+/// How long the next wall is announced before the arena closes in, in frames
+pub const WARNING_FRAMES: usize = 3 * 60;
+
+/// Frames between each blink of the warning
+const WARNING_BLINK_PERIOD: usize = 15;
+
+/// The arena stops closing in once it's down to this many rows or columns
+const MIN_SIDE: i32 = 4;
+
+/// The play area of a survival round, which closes in by one cell from every
+/// side every now and then, until it can't get any smaller.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Arena {
+    pub inset: i32, // how many cells the wall has closed in from every side
+    pub frames_until_close: usize,
+    width: i32,  // of the full play area
+    height: i32, // of the full play area
+}
+
+impl Arena {
+    pub fn new(width: i32, height: i32) -> Self {
+        Arena {
+            inset: 0,
+            frames_until_close: CLOSE_PERIOD_FRAMES,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the arena is still large enough to close in further
+    pub fn can_close(&self) -> bool {
+        let next_inset = self.inset + 1;
+        i32::min(self.width, self.height) - 2 * next_inset >= MIN_SIDE
+    }
+
+    /// Counts down to the next time the arena closes in. Returns whether it
+    /// closed in on this frame.
+    pub fn tick(&mut self) -> bool {
+        if !self.can_close() {
+            return false;
+        }
+        self.frames_until_close -= 1;
+        if self.frames_until_close > 0 {
+            return false;
+        }
+        self.inset += 1;
+        self.frames_until_close = CLOSE_PERIOD_FRAMES;
+        true
+    }
+
+    /// Whether the arena is about to close in, and the next wall should be
+    /// announced
+    pub fn is_warning(&self) -> bool {
+        self.can_close() && self.frames_until_close <= WARNING_FRAMES
+    }
+
+    /// Whether the blinking warning is showing on this frame
+    pub fn warning_visible(&self) -> bool {
+        self.is_warning() && (self.frames_until_close / WARNING_BLINK_PERIOD).is_multiple_of(2)
+    }
+
+    /// Whether `point` lies inside the wall
+    pub fn contains(&self, point: IVec2) -> bool {
+        (self.inset..self.width - self.inset).contains(&point.x)
+            && (self.inset..self.height - self.inset).contains(&point.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::i32;
+
+    #[test]
+    fn arena_closes_in_once_every_period() {
+        let mut arena = Arena::new(20, 10);
+        for _ in 0..CLOSE_PERIOD_FRAMES - 1 {
+            assert!(!arena.tick());
+        }
+        assert!(arena.tick());
+        assert_eq!(arena.inset, 1);
+        assert_eq!(arena.frames_until_close, CLOSE_PERIOD_FRAMES);
+    }
+
+    #[test]
+    fn arena_stops_closing_in_when_small() {
+        let mut arena = Arena::new(20, 10);
+        let closings = (0..10 * CLOSE_PERIOD_FRAMES)
+            .filter(|_| arena.tick())
+            .count();
+        assert_eq!(closings, 3);
+        assert_eq!(arena.inset, 3);
+        assert!(!arena.is_warning());
+    }
+
+    #[test]
+    fn warning_comes_before_closing_in() {
+        let mut arena = Arena::new(20, 10);
+        assert!(!arena.is_warning());
+        arena.frames_until_close = WARNING_FRAMES;
+        assert!(arena.is_warning());
+    }
+
+    #[test]
+    fn closed_off_cells_are_outside() {
+        let mut arena = Arena::new(20, 10);
+        assert!(arena.contains(i32::ivec2(0, 0)));
+        assert!(arena.contains(i32::ivec2(19, 9)));
+        assert!(!arena.contains(i32::ivec2(20, 9)));
+
+        arena.inset = 1;
+        assert!(!arena.contains(i32::ivec2(0, 5)));
+        assert!(!arena.contains(i32::ivec2(19, 5)));
+        assert!(!arena.contains(i32::ivec2(5, 9)));
+        assert!(arena.contains(i32::ivec2(1, 1)));
+        assert!(arena.contains(i32::ivec2(18, 8)));
+    }
+}
@@ -0,0 +1,360 @@
+use crate::input::Keyboard;
+use platform::keyboard::KeyCode;
+use platform::virtual_keycodes;
+use std::cell::Cell;
+use std::io;
+use std::io::Read;
+use std::net::TcpStream;
+use std::rc::Rc;
+
+const IAC: u8 = 255; // "interpret as command"
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250; // start of subnegotiation
+const SE: u8 = 240; // end of subnegotiation
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31; // "negotiate about window size"
+
+const ESCAPE: u8 = 0x1b;
+
+/// Most bytes kept of a subnegotiation, which is as long as a window size one.
+/// Anything longer isn't understood anyway, and keeping it all would let a
+/// client fill up the memory of the server.
+const MAX_SUBNEGOTIATION: usize = 5;
+
+/// Most parameter bytes kept of a control sequence, which is plenty for the
+/// keys there are
+const MAX_CONTROL_PARAMETERS: usize = 8;
+
+/// Sent to a client as soon as it connects: the server does the echoing (and
+/// so nothing gets echoed), keys are sent one by one instead of line by line,
+/// and the client should tell us the size of its window.
+pub const NEGOTIATION: [u8; 9] = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+
+/// Something a telnet client has told us
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TelnetEvent {
+    Key(KeyCode),
+    WindowSize { lines: i32, columns: i32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+enum DecoderState {
+    #[default]
+    Data,
+    Command,                        // after IAC
+    Option,                         // after IAC WILL/WONT/DO/DONT
+    Subnegotiation(Vec<u8>),        // after IAC SB
+    SubnegotiationCommand(Vec<u8>), // after IAC inside a subnegotiation
+    Escape,                         // after ESC
+    ControlSequence(Vec<u8>),       // after ESC [ or ESC O
+    Return,                         // after \r, which may be followed by \n or \0
+}
+
+/// Turns the bytes sent by a telnet client into key presses, skipping over the
+/// telnet commands in between
+#[derive(Debug, Default)]
+pub struct TelnetDecoder {
+    state: DecoderState,
+}
+
+impl TelnetDecoder {
+    /// Decodes the next chunk of `bytes` read from the client
+    pub fn decode(&mut self, bytes: &[u8]) -> Vec<TelnetEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            self.decode_byte(byte, &mut events);
+        }
+        // an escape sequence always arrives all at once, so an escape at the
+        // end of a chunk is the escape key on its own
+        if self.state == DecoderState::Escape {
+            events.push(TelnetEvent::Key(virtual_keycodes::VK_ESCAPE));
+            self.state = DecoderState::Data;
+        }
+        events
+    }
+
+    fn decode_byte(&mut self, byte: u8, events: &mut Vec<TelnetEvent>) {
+        let state = std::mem::replace(&mut self.state, DecoderState::Data);
+        self.state = match state {
+            DecoderState::Data => self.decode_data(byte, events),
+            DecoderState::Return if byte == b'\n' || byte == 0 => DecoderState::Data,
+            DecoderState::Return => self.decode_data(byte, events),
+            DecoderState::Command => match byte {
+                WILL | WONT | DO | DONT => DecoderState::Option,
+                SB => DecoderState::Subnegotiation(Vec::new()),
+                _ => DecoderState::Data,
+            },
+            DecoderState::Option => DecoderState::Data,
+            DecoderState::Subnegotiation(mut data) => {
+                if byte == IAC {
+                    DecoderState::SubnegotiationCommand(data)
+                } else if data.len() < MAX_SUBNEGOTIATION {
+                    data.push(byte);
+                    DecoderState::Subnegotiation(data)
+                } else {
+                    DecoderState::Data
+                }
+            }
+            DecoderState::SubnegotiationCommand(mut data) => match byte {
+                SE => {
+                    events.extend(decode_subnegotiation(&data));
+                    DecoderState::Data
+                }
+                IAC if data.len() < MAX_SUBNEGOTIATION => {
+                    data.push(IAC);
+                    DecoderState::Subnegotiation(data)
+                }
+                _ => DecoderState::Data,
+            },
+            DecoderState::Escape => match byte {
+                b'[' | b'O' => DecoderState::ControlSequence(Vec::new()),
+                _ => {
+                    events.push(TelnetEvent::Key(virtual_keycodes::VK_ESCAPE));
+                    self.decode_data(byte, events)
+                }
+            },
+            DecoderState::ControlSequence(mut parameters) => {
+                if !(byte.is_ascii_digit() || byte == b';') {
+                    if let Some(key) = control_sequence_key(&parameters, byte) {
+                        events.push(TelnetEvent::Key(key));
+                    }
+                    DecoderState::Data
+                } else if parameters.len() < MAX_CONTROL_PARAMETERS {
+                    parameters.push(byte);
+                    DecoderState::ControlSequence(parameters)
+                } else {
+                    DecoderState::Data
+                }
+            }
+        };
+    }
+
+    fn decode_data(&mut self, byte: u8, events: &mut Vec<TelnetEvent>) -> DecoderState {
+        match byte {
+            IAC => return DecoderState::Command,
+            ESCAPE => return DecoderState::Escape,
+            b'\r' => {
+                events.push(TelnetEvent::Key(virtual_keycodes::VK_RETURN));
+                return DecoderState::Return;
+            }
+            _ => {}
+        }
+        if let Some(key) = byte_key(byte) {
+            events.push(TelnetEvent::Key(key));
+        }
+        DecoderState::Data
+    }
+}
+
+/// The key that sends a single `byte`
+fn byte_key(byte: u8) -> Option<KeyCode> {
+    match byte {
+        b'\n' => Some(virtual_keycodes::VK_RETURN),
+        b' ' => Some(virtual_keycodes::VK_SPACE),
+        0x08 | 0x7f => Some(virtual_keycodes::VK_BACK),
+        b'a'..=b'z' => Some(virtual_keycodes::VK_A + (byte - b'a') as KeyCode),
+        b'A'..=b'Z' => Some(virtual_keycodes::VK_A + (byte - b'A') as KeyCode),
+        b'0'..=b'9' => Some(virtual_keycodes::VK_0 + (byte - b'0') as KeyCode),
+        _ => None,
+    }
+}
+
+/// The key that sends the escape sequence ending with `last`
+fn control_sequence_key(parameters: &[u8], last: u8) -> Option<KeyCode> {
+    match (parameters, last) {
+        (_, b'A') => Some(virtual_keycodes::VK_UP),
+        (_, b'B') => Some(virtual_keycodes::VK_DOWN),
+        (_, b'C') => Some(virtual_keycodes::VK_RIGHT),
+        (_, b'D') => Some(virtual_keycodes::VK_LEFT),
+        ([], b'P') | (b"11", b'~') => Some(virtual_keycodes::VK_F1),
+        _ => None,
+    }
+}
+
+fn decode_subnegotiation(data: &[u8]) -> Option<TelnetEvent> {
+    match data {
+        [NAWS, width_high, width_low, height_high, height_low] => Some(TelnetEvent::WindowSize {
+            lines: u16::from_be_bytes([*height_high, *height_low]) as i32,
+            columns: u16::from_be_bytes([*width_high, *width_low]) as i32,
+        }),
+        _ => None,
+    }
+}
+
+/// The keyboard of a player connected with telnet. A closed connection acts
+/// like the escape key being held down, so that the game quits.
+#[derive(Debug)]
+pub struct TelnetKeyboard {
+    stream: TcpStream,
+    decoder: TelnetDecoder,
+    window_size: Rc<Cell<(i32, i32)>>,
+    pressed_keys: Vec<KeyCode>,
+    disconnected: bool,
+}
+
+impl TelnetKeyboard {
+    /// Reads keys from `stream`, and stores the (lines, columns) of the
+    /// client's window in `window_size` whenever the client reports it.
+    /// The stream should have a short read timeout, since it's read once a frame.
+    pub fn new(stream: TcpStream, window_size: Rc<Cell<(i32, i32)>>) -> Self {
+        TelnetKeyboard {
+            stream,
+            decoder: TelnetDecoder::default(),
+            window_size,
+            pressed_keys: Vec::new(),
+            disconnected: false,
+        }
+    }
+}
+
+impl Keyboard for TelnetKeyboard {
+    fn update(&mut self) {
+        self.pressed_keys.clear();
+        let mut buffer = [0; 256];
+        while !self.disconnected {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.disconnected = true,
+                Ok(count) => {
+                    for event in self.decoder.decode(&buffer[..count]) {
+                        match event {
+                            TelnetEvent::Key(key) => self.pressed_keys.push(key),
+                            TelnetEvent::WindowSize { lines, columns } => {
+                                self.window_size.set((lines, columns))
+                            }
+                        }
+                    }
+                    if count < buffer.len() {
+                        break;
+                    }
+                }
+                Err(error) => match error.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => break,
+                    _ => self.disconnected = true,
+                },
+            }
+        }
+        if self.disconnected {
+            self.pressed_keys.push(virtual_keycodes::VK_ESCAPE);
+        }
+    }
+
+    fn key_pressed_now(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    fn any_key_pressed(&self) -> bool {
+        !self.pressed_keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<TelnetEvent> {
+        TelnetDecoder::default().decode(bytes)
+    }
+
+    fn key(key: KeyCode) -> TelnetEvent {
+        TelnetEvent::Key(key)
+    }
+
+    #[test]
+    fn arrow_keys_are_decoded() {
+        assert_eq!(
+            keys(b"\x1b[A\x1b[B\x1bOC\x1b[D"),
+            vec![
+                key(virtual_keycodes::VK_UP),
+                key(virtual_keycodes::VK_DOWN),
+                key(virtual_keycodes::VK_RIGHT),
+                key(virtual_keycodes::VK_LEFT),
+            ]
+        );
+    }
+
+    #[test]
+    fn letters_and_enter_are_decoded() {
+        assert_eq!(
+            keys(b"wD\r\n\r\0"),
+            vec![
+                key(virtual_keycodes::VK_W),
+                key(virtual_keycodes::VK_D),
+                key(virtual_keycodes::VK_RETURN),
+                key(virtual_keycodes::VK_RETURN),
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_escape_is_the_escape_key() {
+        assert_eq!(keys(b"\x1b"), vec![key(virtual_keycodes::VK_ESCAPE)]);
+        assert_eq!(
+            keys(b"\x1bw"),
+            vec![
+                key(virtual_keycodes::VK_ESCAPE),
+                key(virtual_keycodes::VK_W)
+            ]
+        );
+    }
+
+    #[test]
+    fn telnet_commands_are_skipped() {
+        let bytes = [IAC, DO, ECHO, b'a', IAC, WILL, NAWS, b'\r', 0];
+        assert_eq!(
+            keys(&bytes),
+            vec![
+                key(virtual_keycodes::VK_A),
+                key(virtual_keycodes::VK_RETURN)
+            ]
+        );
+    }
+
+    #[test]
+    fn window_size_is_decoded() {
+        let bytes = [IAC, SB, NAWS, 0, 120, 0, 40, IAC, SE, b'x'];
+        assert_eq!(
+            keys(&bytes),
+            vec![
+                TelnetEvent::WindowSize {
+                    lines: 40,
+                    columns: 120
+                },
+                key(virtual_keycodes::VK_X),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlong_sequences_are_given_up_on() {
+        let mut decoder = TelnetDecoder::default();
+        decoder.decode(&[IAC, SB, NAWS]);
+        decoder.decode(&[0; 1000]);
+        assert_eq!(decoder.state, DecoderState::Data);
+
+        decoder.decode(b"\x1b[");
+        decoder.decode(&[b';'; 1000]);
+        assert_eq!(decoder.state, DecoderState::Data);
+        assert_eq!(
+            decoder.decode(b"\x1b[A"),
+            vec![key(virtual_keycodes::VK_UP)]
+        );
+    }
+
+    #[test]
+    fn commands_split_across_reads_are_decoded() {
+        let mut decoder = TelnetDecoder::default();
+        assert_eq!(decoder.decode(&[IAC, SB, NAWS, 0]), vec![]);
+        assert_eq!(
+            decoder.decode(&[IAC, IAC, 0, 30, IAC, SE]),
+            vec![TelnetEvent::WindowSize {
+                lines: 30,
+                columns: 255
+            }]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+const FRAMES_PER_SECOND: usize = 60;
+const FRAMES_PER_MINUTE: usize = 60 * FRAMES_PER_SECOND;
+
+/// How long a time attack round lasts, in frames
+pub const ROUND_FRAMES: usize = 2 * FRAMES_PER_MINUTE;
+
+/// How much time is taken off the clock each time the snake dies, in frames
+pub const DEATH_PENALTY_FRAMES: usize = 10 * FRAMES_PER_SECOND;
+
+/// The clock of a time attack round, which counts down from a fixed duration
+/// and keeps track of how many apples were eaten before time ran out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    pub duration: usize,    // frames
+    pub frames_left: usize, // frames
+    pub apples: usize,
+    pub deaths: usize,
+}
+
+impl Clock {
+    pub fn new(duration: usize) -> Self {
+        Clock {
+            duration,
+            frames_left: duration,
+            apples: 0,
+            deaths: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+
+    /// Takes the death penalty off the clock
+    pub fn record_death(&mut self) {
+        self.deaths += 1;
+        self.frames_left = self.frames_left.saturating_sub(DEATH_PENALTY_FRAMES);
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.frames_left == 0
+    }
+
+    /// Whole seconds left on the clock, rounded up so that the clock shows
+    /// 0:00 only once time is up
+    pub fn seconds_left(&self) -> usize {
+        self.frames_left.div_ceil(FRAMES_PER_SECOND)
+    }
+
+    /// The time left formatted as minutes and seconds, e.g. "1:05"
+    pub fn time_left_text(&self) -> String {
+        let seconds = self.seconds_left();
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    /// The final result of the round. Time lost to deaths still counts
+    /// towards the duration, so dying lowers the rate.
+    pub fn apples_per_minute(&self) -> f32 {
+        if self.duration == 0 {
+            return 0.0;
+        }
+        self.apples as f32 * FRAMES_PER_MINUTE as f32 / self.duration as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_runs_out_after_duration() {
+        let mut clock = Clock::new(3);
+        clock.tick();
+        clock.tick();
+        assert!(!clock.is_up());
+        clock.tick();
+        assert!(clock.is_up());
+        clock.tick();
+        assert_eq!(clock.frames_left, 0);
+    }
+
+    #[test]
+    fn death_takes_time_off_the_clock() {
+        let mut clock = Clock::new(ROUND_FRAMES);
+        clock.record_death();
+        assert_eq!(clock.frames_left, ROUND_FRAMES - DEATH_PENALTY_FRAMES);
+        assert_eq!(clock.deaths, 1);
+
+        let mut clock = Clock::new(DEATH_PENALTY_FRAMES / 2);
+        clock.record_death();
+        assert!(clock.is_up());
+    }
+
+    #[test]
+    fn time_left_is_shown_in_minutes_and_seconds() {
+        let mut clock = Clock::new(ROUND_FRAMES);
+        assert_eq!(clock.time_left_text(), "2:00");
+        clock.tick();
+        assert_eq!(clock.time_left_text(), "2:00");
+        clock.frames_left = 65 * FRAMES_PER_SECOND;
+        assert_eq!(clock.time_left_text(), "1:05");
+        clock.frames_left = 0;
+        assert_eq!(clock.time_left_text(), "0:00");
+    }
+
+    #[test]
+    fn apples_per_minute_is_over_the_whole_duration() {
+        let mut clock = Clock::new(ROUND_FRAMES);
+        clock.apples = 30;
+        assert_eq!(clock.apples_per_minute(), 15.0);
+        clock.record_death();
+        assert_eq!(clock.apples_per_minute(), 15.0);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::rectilinear::RectilinearLine;
+use glam::IVec2;
+
+/// The cells covered by the trails of the light cycles in a tron round.
+///
+/// Trails never shrink, so checking the head of every cycle against every
+/// segment of every trail would get slower the longer a round goes on. Instead
+/// the covered cells are kept in a grid, where a crash is found in constant
+/// time no matter how long the trails get.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailGrid {
+    width: i32,
+    height: i32,
+    owners: Vec<Option<usize>>, // which cycle left a trail in each cell
+}
+
+impl TrailGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        TrailGrid {
+            width,
+            height,
+            owners: vec![None; (width * height) as usize],
+        }
+    }
+
+    fn index(&self, point: IVec2) -> Option<usize> {
+        let inside = (0..self.width).contains(&point.x) && (0..self.height).contains(&point.y);
+        if inside {
+            Some((point.y * self.width + point.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The cycle whose trail covers `point`, if any
+    pub fn owner(&self, point: IVec2) -> Option<usize> {
+        self.index(point).and_then(|index| self.owners[index])
+    }
+
+    pub fn is_covered(&self, point: IVec2) -> bool {
+        self.owner(point).is_some()
+    }
+
+    /// Covers `point` with the trail of cycle `owner`
+    pub fn cover(&mut self, point: IVec2, owner: usize) {
+        if let Some(index) = self.index(point) {
+            self.owners[index] = Some(owner);
+        }
+    }
+
+    /// Covers every point of `line` with the trail of cycle `owner`
+    pub fn cover_line(&mut self, line: &RectilinearLine, owner: usize) {
+        for point in line.points() {
+            self.cover(point, owner);
+        }
+    }
+
+    /// Removes the trail of cycle `owner`, such as when it has crashed
+    pub fn clear_owner(&mut self, owner: usize) {
+        for cell in self.owners.iter_mut() {
+            if *cell == Some(owner) {
+                *cell = None;
+            }
+        }
+    }
+}
+
+/// Finds which cycles have crashed, given the cell each cycle has just moved
+/// its head into, or `None` for the cycles that didn't move. A cycle crashes
+/// when it moves onto any trail, including its own, or into the same cell as
+/// another cycle.
+pub fn find_crashes(trails: &TrailGrid, new_heads: &[Option<IVec2>]) -> Vec<bool> {
+    new_heads
+        .iter()
+        .enumerate()
+        .map(|(index, head)| match head {
+            Some(head) => {
+                trails.is_covered(*head)
+                    || new_heads
+                        .iter()
+                        .enumerate()
+                        .any(|(other, other_head)| other != index && *other_head == Some(*head))
+            }
+            None => false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rectilinear::ChainedLineSegment;
+    use crate::rectilinear::Direction;
+    use glam::i32;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn covered_cells_remember_their_owner() {
+        let mut trails = TrailGrid::new(4, 4);
+        trails.cover(i32::ivec2(1, 2), 3);
+        assert_eq!(trails.owner(i32::ivec2(1, 2)), Some(3));
+        assert!(!trails.is_covered(i32::ivec2(2, 1)));
+    }
+
+    #[test]
+    fn cells_outside_the_grid_are_never_covered() {
+        let mut trails = TrailGrid::new(4, 4);
+        trails.cover(i32::ivec2(-1, 0), 0);
+        trails.cover(i32::ivec2(4, 0), 0);
+        assert!(!trails.is_covered(i32::ivec2(-1, 0)));
+        assert!(!trails.is_covered(i32::ivec2(4, 0)));
+    }
+
+    #[test]
+    fn whole_lines_can_be_covered_and_cleared() {
+        let mut trails = TrailGrid::new(8, 8);
+        let line = RectilinearLine {
+            start: i32::ivec2(1, 1),
+            segments: VecDeque::from(vec![seg!(Direction::Right, 3), seg!(Direction::Down, 2)]),
+        };
+        trails.cover_line(&line, 0);
+        trails.cover(i32::ivec2(6, 6), 1);
+        for point in line.points() {
+            assert_eq!(trails.owner(point), Some(0));
+        }
+
+        trails.clear_owner(0);
+        assert!(line.points().iter().all(|&point| !trails.is_covered(point)));
+        assert!(trails.is_covered(i32::ivec2(6, 6)));
+    }
+
+    #[test]
+    fn moving_onto_a_trail_is_a_crash() {
+        let mut trails = TrailGrid::new(4, 4);
+        trails.cover(i32::ivec2(2, 2), 1);
+        let crashes = find_crashes(&trails, &[Some(i32::ivec2(2, 2)), Some(i32::ivec2(0, 0))]);
+        assert_eq!(crashes, vec![true, false]);
+    }
+
+    #[test]
+    fn moving_into_the_same_cell_crashes_both() {
+        let trails = TrailGrid::new(4, 4);
+        let crashes = find_crashes(
+            &trails,
+            &[Some(i32::ivec2(1, 1)), Some(i32::ivec2(1, 1)), None],
+        );
+        assert_eq!(crashes, vec![true, true, false]);
+    }
+}
@@ -0,0 +1,66 @@
+/// Keeps track of how many rounds each player has won in a versus match,
+/// where the first player to win `wins_needed` rounds wins the match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchScore {
+    pub wins: Vec<usize>,
+    pub wins_needed: usize,
+}
+
+impl MatchScore {
+    pub fn new(players: usize, wins_needed: usize) -> Self {
+        MatchScore {
+            wins: vec![0; players],
+            wins_needed,
+        }
+    }
+
+    /// Records the outcome of a round, where a `winner` of `None` means that
+    /// the round ended in a draw.
+    pub fn record_round(&mut self, winner: Option<usize>) {
+        if let Some(player) = winner {
+            self.wins[player] += 1;
+        }
+    }
+
+    /// The player that has won the match, if any
+    pub fn match_winner(&self) -> Option<usize> {
+        self.wins.iter().position(|&wins| wins >= self.wins_needed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_match_has_no_wins_and_no_winner() {
+        let score = MatchScore::new(2, 3);
+        assert_eq!(score.wins, vec![0, 0]);
+        assert_eq!(score.match_winner(), None);
+    }
+
+    #[test]
+    fn round_winner_is_awarded_a_win() {
+        let mut score = MatchScore::new(2, 3);
+        score.record_round(Some(1));
+        assert_eq!(score.wins, vec![0, 1]);
+    }
+
+    #[test]
+    fn draw_awards_no_wins() {
+        let mut score = MatchScore::new(2, 3);
+        score.record_round(None);
+        assert_eq!(score.wins, vec![0, 0]);
+    }
+
+    #[test]
+    fn first_player_to_reach_needed_wins_wins_the_match() {
+        let mut score = MatchScore::new(2, 3);
+        score.record_round(Some(0));
+        score.record_round(Some(1));
+        score.record_round(Some(0));
+        assert_eq!(score.match_winner(), None);
+        score.record_round(Some(0));
+        assert_eq!(score.match_winner(), Some(0));
+    }
+}